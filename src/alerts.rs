@@ -0,0 +1,157 @@
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::io::{Read, Write};
+
+use crate::ids::{ClientId, TxId};
+use crate::payments::{Transaction, DEPOSIT};
+
+// names of the alert rules
+pub const LARGE_DEPOSIT: &str = "large_deposit";
+pub const LARGE_WITHDRAWAL: &str = "large_withdrawal";
+pub const STRUCTURING: &str = "structuring";
+
+// several deposits from one client just below the reporting threshold
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Structuring {
+    // how far below the threshold a deposit may be, as a fraction of it
+    pub margin: Decimal,
+    // number of such deposits which raise an alert
+    pub count: usize,
+    // seconds within which they have to occur
+    pub window: u64,
+}
+
+// compliance alert settings, nothing is reported by default
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct AlertConfig {
+    // deposits and withdrawals above this amount are reported
+    pub threshold: Option<Decimal>,
+    // thresholds of particular currencies, used instead of the one above
+    #[serde(default)]
+    pub thresholds: HashMap<String, Decimal>,
+    pub structuring: Option<Structuring>,
+}
+
+impl AlertConfig {
+    // the reporting threshold of the passed currency, if it has one
+    pub fn threshold(&self, currency: &str) -> Option<Decimal> {
+        self.thresholds.get(currency).copied().or(self.threshold)
+    }
+
+    // load the alert settings from JSON
+    pub fn load<R: Read>(reader: R) -> Result<AlertConfig, Box<dyn Error>> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+}
+
+// a report for the compliance team, alerts never reject a tx
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Alert {
+    pub client: ClientId,
+    pub rule: String,
+    // the currency of the amounts, the threshold applies to each currency on its own
+    pub currency: String,
+    pub txs: Vec<TxId>,
+    pub amounts: Vec<Decimal>,
+}
+
+// an alert flattened into a single CSV row, lists are separated by semicolons
+#[derive(Serialize)]
+struct AlertRecord<'a> {
+    client: ClientId,
+    rule: &'a str,
+    currency: &'a str,
+    txs: String,
+    amounts: String,
+}
+
+// (time, tx, amount) of recent near threshold deposits
+type Deposits = VecDeque<(u64, TxId, Decimal)>;
+
+// the recent near threshold deposits of each client, by currency
+#[derive(Clone, Debug, Default)]
+pub struct Monitor {
+    deposits: HashMap<(ClientId, String), Deposits>,
+}
+
+impl Monitor {
+    // check an accepted deposit or withdrawal against the alert rules
+    pub fn observe(&mut self, config: &AlertConfig, tx: &Transaction, amount: Decimal, now: u64) -> Option<Alert> {
+        let threshold = config.threshold(&tx.currency)?;
+
+        if amount > threshold {
+            let rule = if tx.tx_type == DEPOSIT { LARGE_DEPOSIT } else { LARGE_WITHDRAWAL };
+
+            return Some(Alert {
+                client: tx.client,
+                rule: rule.to_string(),
+                currency: tx.currency.clone(),
+                txs: vec![tx.tx],
+                amounts: vec![amount],
+            });
+        }
+
+        let structuring = config.structuring.as_ref()?;
+
+        if tx.tx_type != DEPOSIT || amount < threshold * (dec!(1) - structuring.margin) {
+            return None;
+        }
+
+        let deposits = self.deposits.entry((tx.client, tx.currency.clone())).or_default();
+        deposits.push_back((now, tx.tx, amount));
+
+        while let Some(&(time, _, _)) = deposits.front() {
+            if time + structuring.window > now {
+                break;
+            }
+
+            deposits.pop_front();
+        }
+
+        if deposits.len() < structuring.count {
+            return None;
+        }
+
+        // start over, so the same deposits aren't reported twice
+        let (txs, amounts) = deposits.drain(..).map(|(_, id, amt)| (id, amt)).unzip();
+
+        Some(Alert {
+            client: tx.client,
+            rule: STRUCTURING.to_string(),
+            currency: tx.currency.clone(),
+            txs,
+            amounts,
+        })
+    }
+}
+
+// write the passed alerts as CSV
+pub fn write_alerts_csv<W: Write>(writer: W, alerts: &[Alert]) -> Result<(), Box<dyn Error>> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+
+    for alert in alerts {
+        let join = |items: Vec<String>| items.join(";");
+
+        csv_writer.serialize(AlertRecord {
+            client: alert.client,
+            rule: &alert.rule,
+            currency: &alert.currency,
+            txs: join(alert.txs.iter().map(|tx| tx.to_string()).collect()),
+            amounts: join(alert.amounts.iter().map(|amt| amt.to_string()).collect()),
+        })?;
+    }
+
+    csv_writer.flush()?;
+
+    Ok(())
+}
+
+// write the passed alerts as a JSON array
+pub fn write_alerts_json<W: Write>(writer: W, alerts: &[Alert]) -> Result<(), Box<dyn Error>> {
+    serde_json::to_writer_pretty(writer, alerts)?;
+
+    Ok(())
+}
@@ -1,41 +1,401 @@
 use std::env;
 use std::error::Error;
-use std::fs::File;
-use std::io::{BufReader, stdout};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, stdout};
+use std::process;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use payments::{Ledger, Transaction};
+use payments::{decompress, write_nacha_payouts, write_pain001_payouts, read_transactions, write_alerts_csv, write_alerts_json, AccountRecord, AlertConfig, Batch, ClientMap, Config, IdScope, InputFormat, Ledger, Originator, OutputFormat, PayeeDirectory, Precision, RateTable, RiskRule, Rule, Schemas, TraceMap, Transaction, Transactions, Unmapped, WIDE_IDS};
+
+// the input name which reads from stdin
+const STDIN: &str = "-";
+
+const USAGE: &str = "\
+usage: payments [options] [--] [file ...]
+
+Reads txs from each file in turn, or from stdin if there are none or a file is -, and writes the
+client accounts to stdout.  Files may be CSV, JSON Lines, camt.053 XML statements, NACHA ACH files
+or MT940 statements, and gzip or zstd compressed.
+
+options:
+    --input-format FORMAT                  csv, jsonl, camt053, nacha or mt940 for all the inputs, detected
+                                           from their names otherwise
+    --output-format csv|json|jsonl         format of the accounts, csv by default
+    --scale N                              decimal places of output amounts, 4 by default
+    --rounding bankers|half-up|truncate    how output amounts are rounded, half-up by default
+    --strict-rounding                      fail rather than round an output amount
+    --lock-provenance                      add lock_reason and locked_by_tx to the output
+    --authorization-ttl N                  release authorizations after N more txs
+    --settlement immediate|explicit|Ntx|Ns when deposits settle
+    --rules file.json                      velocity rules
+    --risk file.json                       risk rules
+    --alert-config file.json               compliance alert settings
+    --alerts file.json|file.csv            where to write compliance alerts
+    --burn-rejected-ids                    never reuse the id of a rejected tx
+    --id-scope global|client|source        how widely tx ids must be unique
+    --schema file.json                     column mappings of CSV inputs, by file name
+    --admin-input FILE                     read admin txs (unlock, freeze, close and adjust) from FILE,
+                                           in order with the other inputs, they are rejected from
+                                           any other input
+    --trailers                             require CSV inputs to end in a trailer row with their
+                                           count and total, rolling them back if it doesn't match,
+                                           files are read twice but stdin is held in memory
+    --client-map file.csv                  clients of the account references in imports
+    --rates file.csv                       FX rate table for exchanges
+    --house-account CLIENT                 client credited with exchange spreads
+    --payouts file.ach|file.xml            where to write a NACHA or pain.001 file paying out the
+                                           accepted withdrawals
+    --traces file.csv                      trace numbers of the NACHA payouts written so far, read to
+                                           match returns and added to when payouts are written
+    --payees file.csv                      bank accounts of the clients to pay out
+    --originator file.json                 business and account the payouts are made from
+    --help                                 print this message
+";
+
+// command line options
+#[derive(Default)]
+struct Options {
+    // (path, admin) of the input files, STDIN for stdin, in order
+    files: Vec<(String, bool)>,
+    help: bool,
+    provenance: bool,
+    // where to write compliance alerts, as JSON if the name ends in .json and CSV otherwise
+    alerts: Option<String>,
+    precision: Precision,
+    format: OutputFormat,
+    // detected from each file's name if None
+    input_format: Option<InputFormat>,
+    // column mappings of CSV inputs
+    schemas: Schemas,
+    // check CSV inputs against their trailer rows
+    trailers: bool,
+    // account references of imported statements
+    clients: ClientMap,
+    // where to write the payouts, as NACHA if the name ends in .ach and pain.001 otherwise
+    payouts: Option<String>,
+    payees: PayeeDirectory,
+    originator: Option<Originator>,
+    // where the trace numbers of NACHA payouts are kept, and those written so far
+    traces_path: Option<String>,
+    traces: TraceMap,
+    config: Config,
+}
 
 // entrypoint
+#[allow(clippy::single_match)]
 fn main() {
     let args: Vec<String> = env::args().collect();
-    let files = &args[1..]; // first arg is exe
-    let mut ledger = Default::default();
+    let mut options = match parse_args(&args[1..]) { // first arg is exe
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            process::exit(1);
+        }
+    };
+
+    if options.help {
+        print!("{}", USAGE);
+        return;
+    }
+
+    let mut ledger = Ledger::new(options.config.clone());
     
-    for file in files {
-        match process_transactions(file, &mut ledger) {
+    for (file, admin) in &options.files {
+        match process_transactions(file, *admin, &options, &mut ledger) {
             Err(err) => eprintln!("Error reading records from {}: {}", file, err),
             Ok(()) => ()
         }
     }
 
-    let mut csv_writer = csv::Writer::from_writer(stdout());
+    for event in &ledger.risk_events {
+        eprintln!("Risk rule {} moved client {} to {:?} after tx {}: {}", event.rule, event.client, event.status, event.tx, event.detail);
+    }
+
+    if let Some(path) = &options.alerts {
+        if let Err(err) = write_alerts(path, &ledger) {
+            eprintln!("Error writing alerts to {}: {}", path, err);
+        }
+    }
 
-    for account in ledger.accounts.values_mut() {
-        account.rescale(4);
-        if let Err(err) = csv_writer.serialize(&account) {
-            eprintln!("Error writing account {}: {}", account.client, err);
+    // parse_args makes sure there is an originator
+    if let (Some(path), Some(originator)) = (&options.payouts, &options.originator) {
+        if let Err(err) = write_payouts(path, &options.payees, originator, &ledger, &mut options.traces, &options.traces_path) {
+            eprintln!("Error writing payouts to {}: {}", path, err);
         }
     }
+
+    // round everything first, so nothing is written if any amount is inexact in strict mode
+    let mut rounded = Vec::new();
+
+    for account in ledger.accounts.values() {
+        match AccountRecord::rounded(account, &options.precision, options.provenance) {
+            Ok(records) => rounded.push((account.client, records)),
+            Err(err) => {
+                eprintln!("Error rounding account {}: {}", account.client, err);
+                process::exit(1);
+            }
+        }
+    }
+
+    let mut writer = options.format.writer(stdout());
+
+    for (client, records) in &rounded {
+        for record in records {
+            if let Err(err) = writer.write(record) {
+                eprintln!("Error writing account {}: {}", client, err);
+            }
+        }
+    }
+
+    if let Err(err) = writer.finish() {
+        eprintln!("Error writing accounts: {}", err);
+    }
+}
+
+// parse the command line, every arg which isn't an option is an input file, as is every arg after --
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+    let mut files_only = false;
+
+    while let Some(arg) = args.next() {
+        if files_only || arg == STDIN || !arg.starts_with('-') {
+            options.files.push((arg.clone(), false));
+            continue;
+        }
+
+        match arg.as_str() {
+            "--" => files_only = true,
+            "--help" | "-h" => options.help = true,
+            "--lock-provenance" => options.provenance = true,
+            "--input-format" => options.input_format = Some(parse_value(arg, args.next())?),
+            "--output-format" => options.format = parse_value(arg, args.next())?,
+            "--scale" => options.precision.scale = parse_value(arg, args.next())?,
+            "--rounding" => options.precision.rounding = parse_value(arg, args.next())?,
+            "--strict-rounding" => options.precision.strict = true,
+            "--authorization-ttl" => options.config.authorization_ttl = Some(parse_value(arg, args.next())?),
+            "--settlement" => options.config.settlement = parse_value(arg, args.next())?,
+            "--rules" => options.config.rules = load_rules(parse_value(arg, args.next())?)?,
+            "--risk" => options.config.risk = load_risk(parse_value(arg, args.next())?)?,
+            "--alert-config" => options.config.alerts = load_alert_config(parse_value(arg, args.next())?)?,
+            "--alerts" => options.alerts = Some(parse_value(arg, args.next())?),
+            "--burn-rejected-ids" => options.config.burn_rejected_ids = true,
+            "--id-scope" => options.config.id_scope = parse_value(arg, args.next())?,
+            "--admin-input" => options.files.push((parse_value(arg, args.next())?, true)),
+            "--trailers" => options.trailers = true,
+            "--schema" => options.schemas = load_schemas(parse_value(arg, args.next())?)?,
+            "--client-map" => options.clients = load_client_map(parse_value(arg, args.next())?)?,
+            "--rates" => options.config.rates = load_rates(parse_value(arg, args.next())?)?,
+            "--house-account" => options.config.house_account = Some(parse_value(arg, args.next())?),
+            "--payouts" => options.payouts = Some(parse_value(arg, args.next())?),
+            "--payees" => options.payees = load_payees(parse_value(arg, args.next())?)?,
+            "--originator" => options.originator = Some(load_originator(parse_value(arg, args.next())?)?),
+            "--traces" => {
+                let path: String = parse_value(arg, args.next())?;
+                options.traces = load_traces(&path)?;
+                options.traces_path = Some(path);
+            },
+            _ => return Err(format!("Unknown option {}", arg)),
+        }
+    }
+
+    if options.payouts.is_some() && options.originator.is_none() {
+        return Err("--payouts requires --originator".to_string());
+    }
+
+    // without the trace numbers of earlier files they would be reused, and returns couldn't be matched
+    if options.payouts.as_deref().is_some_and(|path| path.ends_with(".ach")) && options.traces_path.is_none() {
+        return Err("NACHA --payouts require --traces".to_string());
+    }
+
+    if options.config.rates.has_spread() && options.config.house_account.is_none() {
+        return Err("--rates with a spread requires --house-account".to_string());
+    }
+
+    if options.files.is_empty() {
+        options.files.push((STDIN.to_string(), false));
+    }
+
+    // without wide ids a trace number's tx id is only unique within its originating bank
+    let nacha = options.files.iter().any(|(file, _)| options.input_format.unwrap_or_else(|| InputFormat::detect(file)) == InputFormat::Nacha);
+
+    if nacha && !WIDE_IDS && options.config.id_scope != IdScope::Source {
+        return Err("NACHA inputs require --id-scope source, or the wide-ids or uuid-ids feature".to_string());
+    }
+
+    Ok(options)
+}
+
+// parse the value which follows an option
+fn parse_value<T: FromStr>(option: &str, value: Option<&String>) -> Result<T, String> {
+    match value.map(|v| v.parse()) {
+        Some(Ok(v)) => Ok(v),
+        _ => Err(format!("{} requires a valid value", option)),
+    }
+}
+
+// load the velocity rules from the passed JSON file
+fn load_rules(path: String) -> Result<Vec<Rule>, String> {
+    let file = File::open(&path).map_err(|err| format!("Error opening rules {}: {}", path, err))?;
+
+    Rule::load(BufReader::new(file)).map_err(|err| format!("Error loading rules {}: {}", path, err))
+}
+
+// load the risk rules from the passed JSON file
+fn load_risk(path: String) -> Result<Vec<RiskRule>, String> {
+    let file = File::open(&path).map_err(|err| format!("Error opening risk rules {}: {}", path, err))?;
+
+    RiskRule::load(BufReader::new(file)).map_err(|err| format!("Error loading risk rules {}: {}", path, err))
+}
+
+// load the compliance alert settings from the passed JSON file
+fn load_alert_config(path: String) -> Result<AlertConfig, String> {
+    let file = File::open(&path).map_err(|err| format!("Error opening alert config {}: {}", path, err))?;
+
+    AlertConfig::load(BufReader::new(file)).map_err(|err| format!("Error loading alert config {}: {}", path, err))
+}
+
+// load the CSV column mappings from the passed JSON file
+fn load_schemas(path: String) -> Result<Schemas, String> {
+    let file = File::open(&path).map_err(|err| format!("Error opening schemas {}: {}", path, err))?;
+
+    Schemas::load(BufReader::new(file)).map_err(|err| format!("Error loading schemas {}: {}", path, err))
+}
+
+// load the client map from the passed CSV file
+fn load_client_map(path: String) -> Result<ClientMap, String> {
+    let file = File::open(&path).map_err(|err| format!("Error opening client map {}: {}", path, err))?;
+
+    ClientMap::load(BufReader::new(file)).map_err(|err| format!("Error loading client map {}: {}", path, err))
+}
+
+// load the FX rate table from the passed CSV file
+fn load_rates(path: String) -> Result<RateTable, String> {
+    let file = File::open(&path).map_err(|err| format!("Error opening rates {}: {}", path, err))?;
+
+    RateTable::load(BufReader::new(file)).map_err(|err| format!("Error loading rates {}: {}", path, err))
+}
+
+// load the payee directory from the passed CSV file
+fn load_payees(path: String) -> Result<PayeeDirectory, String> {
+    let file = File::open(&path).map_err(|err| format!("Error opening payees {}: {}", path, err))?;
+
+    PayeeDirectory::load(BufReader::new(file)).map_err(|err| format!("Error loading payees {}: {}", path, err))
+}
+
+// load the payout originator from the passed JSON file
+fn load_originator(path: String) -> Result<Originator, String> {
+    let file = File::open(&path).map_err(|err| format!("Error opening originator {}: {}", path, err))?;
+
+    Originator::load(BufReader::new(file)).map_err(|err| format!("Error loading originator {}: {}", path, err))
+}
+
+// load the trace numbers of earlier NACHA payouts from the passed CSV file, which may not exist yet
+fn load_traces(path: &str) -> Result<TraceMap, String> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(TraceMap::new()),
+        Err(err) => return Err(format!("Error opening traces {}: {}", path, err)),
+    };
+
+    TraceMap::load(BufReader::new(file)).map_err(|err| format!("Error loading traces {}: {}", path, err))
+}
+
+// write the ledger's alerts to the passed file
+fn write_alerts(path: &str, ledger: &Ledger) -> Result<(), Box<dyn Error>> {
+    let file = File::create(path)?;
+
+    if path.ends_with(".json") {
+        write_alerts_json(file, &ledger.alerts)
+    } else {
+        write_alerts_csv(file, &ledger.alerts)
+    }
 }
 
-// process all transactions in the passed CSV file
-fn process_transactions(path: &String, ledger: &mut Ledger) -> Result<(), Box<dyn Error>> {
-    let file = File::open(path)?;
-    let buf_reader = BufReader::new(file);
+// process all transactions in the passed file
+fn process_transactions(path: &str, admin: bool, options: &Options, ledger: &mut Ledger) -> Result<(), Box<dyn Error>> {
+    let format = options.input_format.unwrap_or_else(|| InputFormat::detect(path));
+    let schemas = &options.schemas;
+
+    if options.trailers && format == InputFormat::Csv {
+        return process_batch(path, admin, options, ledger);
+    }
+
+    let reader = open_input(path)?;
 
-    let mut csv_reader = csv::Reader::from_reader(buf_reader);
-    for result in csv_reader.deserialize() {
-        let tx: Transaction = result?;
+    let txs: Transactions = match (format, schemas.get(path)) {
+        // the header is checked against the schema before any txs are processed
+        (InputFormat::Csv, Some(schema)) => schema.read_transactions(reader)?,
+        (_, Some(_)) => return Err("Schemas only apply to CSV input".into()),
+        (_, None) => read_transactions(reader, format, &options.clients, &options.traces),
+    };
+
+    process_all(path, admin, txs, ledger)
+}
+
+// the decompressed contents of the passed file, or of stdin
+fn open_input(path: &str) -> Result<Box<dyn Read>, Box<dyn Error>> {
+    let input: Box<dyn BufRead> = if path == STDIN {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(path)?))
+    };
+
+    Ok(decompress(input)?)
+}
+
+// process a file with a trailer, none of its txs are processed unless it matches the trailer
+//
+// a file is read twice, once to check it against its trailer and once to process it, so its txs
+// never have to be held in memory, but stdin can only be read once so its txs are held until the
+// trailer has been checked
+fn process_batch(path: &str, admin: bool, options: &Options, ledger: &mut Ledger) -> Result<(), Box<dyn Error>> {
+    let schema = options.schemas.get(path);
+    let batch = Batch::read(open_input(path)?, schema)?;
+
+    if path == STDIN {
+        let txs = match batch.staged() {
+            Ok(txs) => txs,
+            Err(err) => return Err(format!("{}, none of its txs were processed", err).into()),
+        };
+
+        return process_all(path, admin, txs.into_iter().map(Ok), ledger);
+    }
+
+    if let Err(err) = batch.check() {
+        return Err(format!("{}, none of its txs were processed", err).into());
+    }
+
+    process_all(path, admin, Batch::read(open_input(path)?, schema)?, ledger)
+}
+
+// process the passed txs in order, a tx which can't be read stops the rest, only txs from an admin
+// input may be admin txs
+fn process_all<I: Iterator<Item = Result<Transaction, Box<dyn Error>>>>(path: &str, admin: bool, txs: I, ledger: &mut Ledger) -> Result<(), Box<dyn Error>> {
+    // the same file passed by different paths is the same source, stdin has no canonical path
+    let source = fs::canonicalize(path)
+        .map(|canonical| canonical.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| path.to_string());
+
+    for result in txs {
+        let mut tx = match result {
+            Ok(tx) => tx,
+            // entries of an imported file which couldn't be mapped are reported and skipped
+            Err(err) => match err.downcast_ref::<Unmapped>() {
+                Some(entry) => {
+                    eprintln!("Unmapped entry {} for account {} in {}: {}", entry.reference, entry.account, path, entry.reason);
+                    continue;
+                },
+                None => return Err(err),
+            },
+        };
+        tx.admin = admin;
+
+        // each input file is its own source, unless the rows name one
+        if tx.source.is_none() {
+            tx.source = Some(source.clone());
+        }
 
         if let Err(err) = ledger.process(&tx) {
             eprintln!("Error processing tx {} for client {}: {}", tx.tx, tx.client, err); 
@@ -44,3 +404,27 @@ fn process_transactions(path: &String, ledger: &mut Ledger) -> Result<(), Box<dy
 
     Ok(())
 }
+
+// write the ledger's payouts to the passed file, dated now, or not at all if they don't add up,
+// and the trace numbers of NACHA payouts to the traces file
+fn write_payouts(path: &str, payees: &PayeeDirectory, originator: &Originator, ledger: &Ledger, traces: &mut TraceMap, traces_path: &Option<String>) -> Result<(), Box<dyn Error>> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let mut data = Vec::new();
+
+    if path.ends_with(".ach") {
+        write_nacha_payouts(&mut data, &ledger.payouts, ledger.payout_total(), payees, originator, now, traces)?;
+    } else {
+        write_pain001_payouts(&mut data, &ledger.payouts, ledger.payout_total(), payees, originator, now)?;
+    }
+
+    fs::write(path, data)?;
+
+    // parse_args makes sure there is a traces file for NACHA payouts
+    if let (true, Some(traces_path)) = (path.ends_with(".ach"), traces_path) {
+        let mut csv = Vec::new();
+        traces.write(&mut csv)?;
+        fs::write(traces_path, csv)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::error::Error;
+use std::io::BufRead;
+
+use crate::ids::TxId;
+use crate::import::{parse_date, ClientMap, Import};
+use crate::payments::{Transaction, DEPOSIT, WITHDRAWAL};
+
+// the parts of a camt.053 entry which are needed for a tx
+#[derive(Default)]
+struct Entry {
+    reference: String,
+    amount: String,
+    currency: String,
+    indicator: String,
+    status: String,
+    date: String,
+    reversal: bool,
+    debtor_account: String,
+    creditor_account: String,
+}
+
+// true if the current element path ends with the passed element names
+fn at(path: &[String], suffix: &[&str]) -> bool {
+    path.len() >= suffix.len() && path[path.len() - suffix.len()..].iter().zip(suffix).all(|(a, b)| a == b)
+}
+
+// true if the current element path is an account id below the passed element
+fn at_account(path: &[String], account: &str) -> bool {
+    at(path, &[account, "Id", "IBAN"]) || at(path, &[account, "Id", "Othr", "Id"])
+}
+
+// import the booked entries of an ISO 20022 camt.053 statement, credits become deposits and
+// debits withdrawals
+//
+// the client is found from the counterparty's account, the debtor for credits and the creditor for
+// debits, falling back to the statement's account, and the tx id is the entry's NtryRef
+pub fn import_camt053<R: BufRead>(reader: R, clients: &ClientMap) -> Result<Import, Box<dyn Error>> {
+    let mut reader = Reader::from_reader(reader);
+    reader.config_mut().trim_text(true);
+
+    let mut import = Import::default();
+    let mut buf = Vec::new();
+    let mut path: Vec<String> = Vec::new();
+    let mut statement_account = String::new();
+    let mut entry: Option<Entry> = None;
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+
+                if name == "Stmt" {
+                    statement_account.clear();
+                } else if name == "Ntry" {
+                    entry = Some(Entry::default());
+                } else if let Some(entry) = entry.as_mut() {
+                    if name == "Amt" && at(&path, &["Ntry"]) {
+                        if let Some(ccy) = e.try_get_attribute("Ccy")? {
+                            entry.currency = ccy.unescape_value()?.into_owned();
+                        }
+                    }
+                }
+
+                path.push(name);
+            },
+            Event::Text(e) => {
+                let text = e.unescape()?.into_owned();
+
+                if at(&path, &["Stmt", "Acct", "Id", "IBAN"]) || at(&path, &["Stmt", "Acct", "Id", "Othr", "Id"]) {
+                    statement_account = text;
+                } else if let Some(entry) = entry.as_mut() {
+                    if at(&path, &["Ntry", "NtryRef"]) {
+                        entry.reference = text;
+                    } else if at(&path, &["Ntry", "Amt"]) {
+                        entry.amount = text;
+                    } else if at(&path, &["Ntry", "CdtDbtInd"]) {
+                        entry.indicator = text;
+                    } else if at(&path, &["Ntry", "Sts"]) || at(&path, &["Ntry", "Sts", "Cd"]) {
+                        entry.status = text;
+                    } else if at(&path, &["Ntry", "BookgDt", "Dt"]) || at(&path, &["Ntry", "BookgDt", "DtTm"]) {
+                        entry.date = text;
+                    } else if at(&path, &["Ntry", "RvslInd"]) {
+                        entry.reversal = text == "true";
+                    } else if at_account(&path, "DbtrAcct") && entry.debtor_account.is_empty() {
+                        entry.debtor_account = text;
+                    } else if at_account(&path, "CdtrAcct") && entry.creditor_account.is_empty() {
+                        entry.creditor_account = text;
+                    }
+                }
+            },
+            Event::End(_) => {
+                let finished = match path.pop().as_deref() {
+                    Some("Ntry") => entry.take(),
+                    _ => None,
+                };
+
+                if let Some(entry) = finished {
+                    map_entry(entry, &statement_account, clients, &mut import);
+                }
+            },
+            Event::Eof => break,
+            _ => (),
+        }
+
+        buf.clear();
+    }
+
+    Ok(import)
+}
+
+// turn a statement entry into a tx, or record why it can't be
+fn map_entry(entry: Entry, statement_account: &str, clients: &ClientMap, import: &mut Import) {
+    let (tx_type, counterparty) = match entry.indicator.as_str() {
+        "CRDT" => (DEPOSIT, &entry.debtor_account),
+        "DBIT" => (WITHDRAWAL, &entry.creditor_account),
+        _ => return import.unmapped(&entry.reference, "", "unknown credit/debit indicator"),
+    };
+
+    if entry.status != "BOOK" {
+        return import.unmapped(&entry.reference, counterparty, "entry not booked");
+    }
+
+    if entry.reversal {
+        return import.unmapped(&entry.reference, counterparty, "reversal entry");
+    }
+
+    let (account, client) = match clients.get(counterparty) {
+        Some(client) => (counterparty.as_str(), client),
+        None => match clients.get(statement_account) {
+            Some(client) => (statement_account, client),
+            None => return import.unmapped(&entry.reference, counterparty, "no client for account"),
+        },
+    };
+
+    let tx: TxId = match entry.reference.parse() {
+        Ok(tx) => tx,
+        Err(_) => return import.unmapped(&entry.reference, account, "entry reference is not a tx id"),
+    };
+
+    import.transactions.push(Transaction {
+        tx_type: tx_type.to_string(),
+        client,
+        tx,
+        amount: entry.amount,
+        currency: entry.currency,
+        timestamp: parse_date(&entry.date),
+        imported: true,
+        ..Default::default()
+    });
+}
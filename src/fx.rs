@@ -0,0 +1,103 @@
+use rust_decimal::prelude::RoundingStrategy;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::Deserialize;
+use std::error::Error;
+use std::io::Read;
+
+use crate::payments::PaymentError;
+
+// the price of one currency in another from a point in time
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Rate {
+    pub from: String,
+    pub to: String,
+    // units of the to currency per unit of the from currency
+    pub rate: Decimal,
+    // fraction of the converted amount kept by the house
+    #[serde(default)]
+    pub spread: Decimal,
+    // seconds since the unix epoch from which the rate applies
+    #[serde(default)]
+    pub effective_from: u64,
+}
+
+// all the rates which exchanges are priced from
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RateTable {
+    rates: Vec<Rate>,
+}
+
+// the result of pricing an exchange
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quote {
+    pub rate: Decimal,
+    // credited to the client, in the to currency
+    pub converted: Decimal,
+    // credited to the house account, in the to currency
+    pub spread: Decimal,
+}
+
+impl RateTable {
+    // ctor
+    pub fn new(rates: Vec<Rate>) -> RateTable {
+        RateTable { rates }
+    }
+
+    // load a rate table from CSV with from,to,rate,spread,effective_from columns
+    pub fn load<R: Read>(reader: R) -> Result<RateTable, Box<dyn Error>> {
+        let mut csv_reader = csv::Reader::from_reader(reader);
+        let mut rates = Vec::new();
+
+        for result in csv_reader.deserialize() {
+            rates.push(result?);
+        }
+
+        Ok(RateTable { rates })
+    }
+
+    // true if any rate keeps a spread, which needs a house account to be credited to
+    pub fn has_spread(&self) -> bool {
+        self.rates.iter().any(|r| r.spread != Decimal::ZERO)
+    }
+
+    // the latest rate from one currency to another which is effective at the passed time
+    pub fn lookup(&self, from: &str, to: &str, now: u64) -> Option<&Rate> {
+        self.rates.iter()
+            .filter(|r| r.from == from && r.to == to && r.effective_from <= now)
+            .max_by_key(|r| r.effective_from)
+    }
+
+    // price the exchange of an amount from one currency to another
+    //
+    // the client's amount and the converted amount are both rounded down to the minor unit of
+    // the to currency, and the house gets the difference, so the same input always gives the same
+    // split and a rate without a spread leaves the house nothing, only a remainder smaller than
+    // the minor unit which nobody is credited with
+    pub fn quote(&self, from: &str, to: &str, amount: Decimal, now: u64) -> Result<Quote, PaymentError> {
+        let rate = match self.lookup(from, to, now) {
+            Some(rate) => rate,
+            None => return Err(PaymentError::RateNotFound),
+        };
+
+        let scale = minor_units(to);
+        let gross = amount * rate.rate;
+        let converted = (gross * (dec!(1) - rate.spread)).round_dp_with_strategy(scale, RoundingStrategy::ToZero);
+        let spread = gross.round_dp_with_strategy(scale, RoundingStrategy::ToZero) - converted;
+
+        Ok(Quote {
+            rate: rate.rate,
+            converted,
+            spread: spread.max(dec!(0)),
+        })
+    }
+}
+
+// number of decimal places in the minor unit of the passed ISO 4217 currency
+pub fn minor_units(currency: &str) -> u32 {
+    match currency {
+        "BIF" | "CLP" | "DJF" | "GNF" | "ISK" | "JPY" | "KMF" | "KRW" | "PYG" | "RWF" | "UGX" | "VND" | "VUV" | "XAF" | "XOF" | "XPF" => 0,
+        "BHD" | "IQD" | "JOD" | "KWD" | "LYD" | "OMR" | "TND" => 3,
+        _ => 2,
+    }
+}
@@ -0,0 +1,42 @@
+// client and tx id types, chosen at build time
+//
+// the defaults are u16 clients and u32 txs, the wide-ids feature widens both to u64, and the
+// uuid-ids feature uses UUIDs for both, read from and written as their hyphenated string form
+//
+// features are additive, so uuid-ids wins if both are enabled, e.g. by --all-features
+
+#[cfg(not(any(feature = "wide-ids", feature = "uuid-ids")))]
+pub type ClientId = u16;
+#[cfg(not(any(feature = "wide-ids", feature = "uuid-ids")))]
+pub type TxId = u32;
+
+#[cfg(all(feature = "wide-ids", not(feature = "uuid-ids")))]
+pub type ClientId = u64;
+#[cfg(all(feature = "wide-ids", not(feature = "uuid-ids")))]
+pub type TxId = u64;
+
+#[cfg(feature = "uuid-ids")]
+pub type ClientId = uuid::Uuid;
+#[cfg(feature = "uuid-ids")]
+pub type TxId = uuid::Uuid;
+
+// true if tx ids can hold any number from a bank file, e.g. a whole NACHA trace number
+pub const WIDE_IDS: bool = cfg!(any(feature = "wide-ids", feature = "uuid-ids"));
+
+// the tx id of a number from a bank file, e.g. a NACHA trace number, None if it doesn't fit
+#[cfg(not(any(feature = "wide-ids", feature = "uuid-ids")))]
+pub(crate) fn tx_id_from(number: u64) -> Option<TxId> {
+    use std::convert::TryFrom;
+
+    TxId::try_from(number).ok()
+}
+
+#[cfg(all(feature = "wide-ids", not(feature = "uuid-ids")))]
+pub(crate) fn tx_id_from(number: u64) -> Option<TxId> {
+    Some(number)
+}
+
+#[cfg(feature = "uuid-ids")]
+pub(crate) fn tx_id_from(number: u64) -> Option<TxId> {
+    Some(uuid::Uuid::from_u128(number.into()))
+}
@@ -0,0 +1,129 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::Read;
+
+use crate::ids::ClientId;
+use crate::payments::Transaction;
+
+// maps the account references used by banks and payment networks to clients
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClientMap {
+    clients: HashMap<String, ClientId>,
+}
+
+// one row of a client map file
+#[derive(Deserialize)]
+struct ClientMapping {
+    reference: String,
+    client: ClientId,
+}
+
+impl ClientMap {
+    // ctor
+    pub fn new() -> ClientMap {
+        Default::default()
+    }
+
+    // load a client map from CSV with reference,client columns
+    pub fn load<R: Read>(reader: R) -> Result<ClientMap, Box<dyn Error>> {
+        let mut csv_reader = csv::Reader::from_reader(reader);
+        let mut clients = ClientMap::new();
+
+        for result in csv_reader.deserialize() {
+            let mapping: ClientMapping = result?;
+            clients.insert(&mapping.reference, mapping.client);
+        }
+
+        Ok(clients)
+    }
+
+    // map an account reference to a client, ignoring surrounding whitespace as get does
+    pub fn insert(&mut self, reference: &str, client: ClientId) {
+        self.clients.insert(reference.trim().to_string(), client);
+    }
+
+    // the client the passed account reference belongs to
+    pub fn get(&self, reference: &str) -> Option<ClientId> {
+        self.clients.get(reference.trim()).copied()
+    }
+}
+
+// an entry of an imported file which couldn't be turned into a tx
+#[derive(Clone, Debug, PartialEq)]
+pub struct Unmapped {
+    // the entry's own reference, as it appears in the file
+    pub reference: String,
+    // the account reference the client was looked up by, if any
+    pub account: String,
+    pub reason: String,
+}
+
+impl fmt::Display for Unmapped {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Unmapped entry {} for account {}: {}", self.reference, self.account, self.reason)
+    }
+}
+
+// read_transactions reports unmapped entries as errors
+impl Error for Unmapped {}
+
+// the result of importing a file
+#[derive(Clone, Debug, Default)]
+pub struct Import {
+    // in the order they appear in the file
+    pub transactions: Vec<Transaction>,
+    pub unmapped: Vec<Unmapped>,
+}
+
+impl Import {
+    pub(crate) fn unmapped(&mut self, reference: &str, account: &str, reason: &str) {
+        self.unmapped.push(Unmapped {
+            reference: reference.to_string(),
+            account: account.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+}
+
+// seconds since the unix epoch at the start of a YYYY-MM-DD date, anything after the date is ignored
+pub fn parse_date(date: &str) -> Option<u64> {
+    let year: i64 = date.get(0..4)?.parse().ok()?;
+    let month: i64 = date.get(5..7)?.parse().ok()?;
+    let day: i64 = date.get(8..10)?.parse().ok()?;
+
+    if date.get(4..5)? != "-" || date.get(7..8)? != "-" || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // days from civil, see http://howardhinnant.github.io/date_algorithms.html
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    if days < 0 {
+        None
+    } else {
+        Some(days as u64 * 86400)
+    }
+}
+
+// the YYYY-MM-DD date of the passed seconds since the unix epoch
+pub fn format_date(secs: u64) -> String {
+    // civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = (secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
@@ -0,0 +1,269 @@
+use csv::StringRecord;
+use flate2::bufread::MultiGzDecoder;
+use rust_decimal::Decimal;
+use serde_json::Value;
+use std::error::Error;
+use std::io::{self, BufRead, BufReader, Read};
+use std::iter;
+use std::str::FromStr;
+
+use crate::camt::import_camt053;
+use crate::import::{ClientMap, Import};
+use crate::mt940::import_mt940;
+use crate::nacha::{import_nacha, TraceMap};
+use crate::payments::Transaction;
+use crate::schema::Schema;
+
+// the types of the rows which start and end a batch
+pub const HEADER: &str = "header";
+pub const TRAILER: &str = "trailer";
+
+// the formats txs can be read in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputFormat {
+    #[default]
+    Csv,
+    // one JSON object per line
+    JsonLines,
+    // ISO 20022 bank statements, see import_camt053
+    Camt053,
+    // NACHA ACH files, see import_nacha
+    Nacha,
+    // SWIFT MT940 statements, see import_mt940
+    Mt940,
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    // parse "csv", "jsonl", "camt053", "nacha" or "mt940"
+    fn from_str(s: &str) -> Result<InputFormat, String> {
+        match s {
+            "csv" => Ok(InputFormat::Csv),
+            "jsonl" => Ok(InputFormat::JsonLines),
+            "camt053" => Ok(InputFormat::Camt053),
+            "nacha" => Ok(InputFormat::Nacha),
+            "mt940" => Ok(InputFormat::Mt940),
+            _ => Err(format!("Bad input format {}", s)),
+        }
+    }
+}
+
+impl InputFormat {
+    // guess the format of a file from its name, CSV unless it ends in .jsonl, .ndjson, .xml, .ach,
+    // .sta or .mt940, ignoring any compression suffix
+    pub fn detect(path: &str) -> InputFormat {
+        let path = [".gz", ".zst", ".zstd"].iter().find_map(|ext| path.strip_suffix(ext)).unwrap_or(path);
+
+        if path.ends_with(".jsonl") || path.ends_with(".ndjson") {
+            InputFormat::JsonLines
+        } else if path.ends_with(".xml") {
+            InputFormat::Camt053
+        } else if path.ends_with(".ach") {
+            InputFormat::Nacha
+        } else if path.ends_with(".sta") || path.ends_with(".mt940") {
+            InputFormat::Mt940
+        } else {
+            InputFormat::Csv
+        }
+    }
+}
+
+// leading bytes of the compressed formats
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+// decompress the passed input if it starts with gzip or zstd magic bytes, otherwise pass it
+// through, either way it is streamed rather than read into memory
+pub fn decompress<'a, R: BufRead + 'a>(mut reader: R) -> io::Result<Box<dyn Read + 'a>> {
+    let header = reader.fill_buf()?;
+
+    if header.starts_with(GZIP_MAGIC) {
+        // concatenated gzip members are read as one stream, like gunzip does
+        Ok(Box::new(MultiGzDecoder::new(reader)))
+    } else if header.starts_with(ZSTD_MAGIC) {
+        Ok(Box::new(zstd::Decoder::with_buffer(reader)?))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+// the txs read from the passed input, in order
+pub type Transactions<'a> = Box<dyn Iterator<Item = Result<Transaction, Box<dyn Error>>> + 'a>;
+
+// raw CSV rows, to be deserialized with their header
+pub(crate) type Rows<'a> = Box<dyn Iterator<Item = Result<StringRecord, Box<dyn Error>>> + 'a>;
+
+// read txs in the passed format, the input must already be decompressed
+//
+// bank files are imported whole, with their account references looked up in clients and the
+// returns of NACHA payouts in traces, and each entry which can't be mapped is an Unmapped error,
+// which callers can report and skip
+pub fn read_transactions<'a, R: Read + 'a>(reader: R, format: InputFormat, clients: &ClientMap, traces: &TraceMap) -> Transactions<'a> {
+    match format {
+        InputFormat::Csv => {
+            let csv_reader = csv::Reader::from_reader(reader);
+            Box::new(csv_reader.into_deserialize().map(|result| result.map_err(|err| err.into())))
+        },
+        InputFormat::JsonLines => {
+            let lines = BufReader::new(reader).lines();
+            Box::new(lines.filter_map(|line| match line {
+                Ok(line) if line.trim().is_empty() => None,
+                Ok(line) => Some(parse_json_line(&line)),
+                Err(err) => Some(Err(err.into())),
+            }))
+        },
+        InputFormat::Camt053 => imported(import_camt053(BufReader::new(reader), clients)),
+        InputFormat::Nacha => imported(import_nacha(BufReader::new(reader), clients, traces)),
+        InputFormat::Mt940 => imported(import_mt940(BufReader::new(reader), clients)),
+    }
+}
+
+// the unmapped entries of an imported file, then its txs
+fn imported(result: Result<Import, Box<dyn Error>>) -> Transactions<'static> {
+    match result {
+        Ok(import) => {
+            let unmapped = import.unmapped.into_iter().map(|entry| Err(entry.into()));
+            Box::new(unmapped.chain(import.transactions.into_iter().map(Ok)))
+        },
+        Err(err) => Box::new(iter::once(Err(err))),
+    }
+}
+
+// parse one line of JSON Lines input, the amount can be a string or a number
+pub fn parse_json_line(line: &str) -> Result<Transaction, Box<dyn Error>> {
+    let mut value: Value = serde_json::from_str(line)?;
+
+    // numbers keep their exact digits, since serde_json is built with arbitrary_precision
+    if let Some(amount) = value.get_mut("amount") {
+        let text = match amount {
+            Value::Number(number) => Some(number.to_string()),
+            Value::Null => Some(String::new()),
+            _ => None,
+        };
+
+        if let Some(text) = text {
+            *amount = Value::String(text);
+        }
+    }
+
+    Ok(serde_json::from_value(value)?)
+}
+
+// the control totals of a batch, its number of tx rows and the sum of their amounts
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Trailer {
+    pub count: u64,
+    pub total: Decimal,
+}
+
+// CSV txs between an optional header row and a trailer row with their control totals, e.g.
+// trailer,,3,60.50 after three tx rows whose amounts add up to 60.50
+pub struct Batch<'a> {
+    header: StringRecord,
+    rows: Rows<'a>,
+    // the totals of the tx rows read so far
+    read: Trailer,
+    trailer: Option<Trailer>,
+}
+
+impl<'a> Batch<'a> {
+    // start reading a batch, through the passed schema if there is one
+    pub fn read<R: Read + 'a>(reader: R, schema: Option<&Schema>) -> Result<Batch<'a>, Box<dyn Error>> {
+        let (header, rows) = match schema {
+            Some(schema) => schema.rows(reader)?,
+            None => {
+                let mut csv_reader = csv::Reader::from_reader(reader);
+                let header = csv_reader.headers()?.clone();
+                let rows: Rows = Box::new(csv_reader.into_records().map(|result| result.map_err(|err| err.into())));
+                (header, rows)
+            },
+        };
+
+        Ok(Batch { header, rows, read: Trailer::default(), trailer: None })
+    }
+
+    // the value of the passed column in a row, empty if there is no such column
+    fn field<'r>(&self, row: &'r StringRecord, name: &str) -> &'r str {
+        self.header.iter().position(|column| column == name).and_then(|i| row.get(i)).unwrap_or("").trim()
+    }
+
+    // the trailer, the number of txs is in the tx column and their total in the amount column
+    fn parse_trailer(&self, row: &StringRecord) -> Result<Trailer, Box<dyn Error>> {
+        Ok(Trailer {
+            count: self.field(row, "tx").parse()?,
+            total: self.field(row, "amount").parse()?,
+        })
+    }
+
+    // check what was read against the trailer, once every tx has been
+    pub fn verify(&self) -> Result<(), String> {
+        match self.trailer {
+            None => Err("No trailer, the file may be truncated".to_string()),
+            Some(trailer) if trailer.count != self.read.count => {
+                Err(format!("Trailer count {} doesn't match the {} txs read", trailer.count, self.read.count))
+            },
+            Some(trailer) if trailer.total != self.read.total => {
+                Err(format!("Trailer total {} doesn't match the {} read", trailer.total, self.read.total))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    // read every tx without keeping any and check them against the trailer, for inputs which can be
+    // read again to process them, so memory stays bounded however large the file is
+    pub fn check(mut self) -> Result<(), Box<dyn Error>> {
+        for result in self.by_ref() {
+            result?;
+        }
+
+        Ok(self.verify()?)
+    }
+
+    // read every tx and check them against the trailer, so a file is applied whole or not at all,
+    // holding every tx in memory for inputs which can only be read once
+    pub fn staged(mut self) -> Result<Vec<Transaction>, Box<dyn Error>> {
+        let txs = self.by_ref().collect::<Result<Vec<_>, _>>()?;
+        self.verify()?;
+
+        Ok(txs)
+    }
+}
+
+impl Iterator for Batch<'_> {
+    type Item = Result<Transaction, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let row = match self.rows.next()? {
+                Ok(row) => row,
+                Err(err) => return Some(Err(err)),
+            };
+
+            if self.trailer.is_some() {
+                return Some(Err("Row after the trailer".into()));
+            }
+
+            let tx_type = self.field(&row, "type");
+
+            if tx_type == HEADER && self.read.count == 0 {
+                continue;
+            }
+
+            if tx_type == TRAILER {
+                match self.parse_trailer(&row) {
+                    Ok(trailer) => self.trailer = Some(trailer),
+                    Err(err) => return Some(Err(format!("Bad trailer: {}", err).into())),
+                }
+
+                continue;
+            }
+
+            // amounts which aren't numbers will be rejected anyway, and are left out of the total
+            self.read.count += 1;
+            self.read.total += self.field(&row, "amount").parse::<Decimal>().unwrap_or_default();
+
+            return Some(row.deserialize(Some(&self.header)).map_err(|err| err.into()));
+        }
+    }
+}
@@ -1,3 +1,31 @@
+mod alerts;
+mod camt;
+mod fx;
+mod ids;
+mod import;
+mod input;
+mod mt940;
+mod nacha;
+mod output;
 mod payments;
+mod payout;
+mod risk;
+mod rules;
+mod schema;
+mod validate;
 
-pub use self::payments::{Account, Ledger, Transaction, PaymentError, DEPOSIT, WITHDRAWAL, DISPUTE, RESOLVE, CHARGEBACK};
+pub use self::alerts::{write_alerts_csv, write_alerts_json, Alert, AlertConfig, Structuring, LARGE_DEPOSIT, LARGE_WITHDRAWAL, STRUCTURING};
+pub use self::camt::import_camt053;
+pub use self::fx::{minor_units, Quote, Rate, RateTable};
+pub use self::ids::{ClientId, TxId, WIDE_IDS};
+pub use self::import::{format_date, parse_date, ClientMap, Import, Unmapped};
+pub use self::input::{decompress, parse_json_line, read_transactions, Batch, InputFormat, Trailer, Transactions, HEADER, TRAILER};
+pub use self::mt940::import_mt940;
+pub use self::nacha::{import_nacha, trace_source, trace_tx_id, PayoutTrace, TraceMap};
+pub use self::output::{AccountRecord, AccountWriter, CsvAccountWriter, JsonAccountWriter, JsonLinesAccountWriter, OutputFormat, Precision, Rounding};
+pub use self::payments::{is_admin, Account, AccountStatus, Balance, Config, IdScope, LockEvent, Ledger, Outcome, Settlement, Transaction, TxKey, PaymentError, DEPOSIT, WITHDRAWAL, DISPUTE, RESOLVE, CHARGEBACK, AUTHORIZE, CAPTURE, VOID, EXCHANGE, SETTLE, RETURNED, UNLOCK, FREEZE, ADJUST, CLOSE};
+pub use self::payout::{valid_routing, write_nacha_payouts, write_pain001_payouts, Originator, Payee, PayeeDirectory, Payout};
+pub use self::risk::{RiskEvent, RiskRule, RiskStats, Threshold};
+pub use self::rules::{Limit, Rule, RuleHit, Window};
+pub use self::schema::{Schema, Schemas, FIELDS};
+pub use self::validate::{check_duplicate, check_funds, check_schema, check_status, owns_id, Validated};
@@ -0,0 +1,244 @@
+use std::error::Error;
+use std::io::BufRead;
+
+use crate::ids::TxId;
+use crate::import::{parse_date, ClientMap, Import};
+use crate::payments::{Transaction, DEPOSIT, WITHDRAWAL};
+
+// the reference banks use for a statement line which has none
+const NONREF: &str = "NONREF";
+
+// a :61: statement line, with the :86: information which follows it
+#[derive(Default)]
+struct Entry {
+    date: String,
+    credit: bool,
+    reversal: bool,
+    amount: String,
+    reference: String,
+    bank_reference: String,
+    info: String,
+}
+
+// the account references found in the structured subfields of a :86: field, in order, none for
+// free text
+struct Info {
+    accounts: Vec<String>,
+}
+
+// split a field line into its tag and the rest, e.g. ":61:2401..." into ("61", "2401...")
+fn tag(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix(':')?;
+    let end = rest.find(':')?;
+
+    if end == 0 || end > 3 || !rest[..end].chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+
+    Some((&rest[..end], &rest[end + 1..]))
+}
+
+// the tagged fields of the passed input, with continuation lines joined by newlines and the
+// SWIFT message envelope left out
+fn fields<R: BufRead>(reader: R) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let mut fields: Vec<(String, String)> = Vec::new();
+
+    for line in reader.split(b'\n') {
+        // statements are often Latin-1 rather than UTF-8
+        let line = String::from_utf8_lossy(&line?).into_owned();
+        let line = line.trim_end_matches('\r');
+
+        if let Some((name, value)) = tag(line) {
+            fields.push((name.to_string(), value.to_string()));
+        } else if line.starts_with('{') || line.starts_with('-') || line.trim().is_empty() {
+            // block headers, {1:F01...}{2:...}{4:, and the -} which ends a message
+            continue;
+        } else if let Some((_, value)) = fields.last_mut() {
+            value.push('\n');
+            value.push_str(line);
+        }
+    }
+
+    Ok(fields)
+}
+
+// parse a :61: statement line, YYMMDD[MMDD](C|D|RC|RD)[funds code]amount type reference[//bank reference]
+fn parse_entry(line: &str) -> Option<Entry> {
+    // supplementary details on the second line aren't needed
+    let line = line.lines().next()?;
+    let date = line.get(0..6)?;
+
+    if !date.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let mut rest = &line[6..];
+
+    // optional entry date
+    if rest.get(0..4).is_some_and(|entry_date| entry_date.chars().all(|c| c.is_ascii_digit())) {
+        rest = &rest[4..];
+    }
+
+    let (credit, reversal, mut rest) = if let Some(rest) = rest.strip_prefix("RC") {
+        (true, true, rest)
+    } else if let Some(rest) = rest.strip_prefix("RD") {
+        (false, true, rest)
+    } else if let Some(rest) = rest.strip_prefix('C') {
+        (true, false, rest)
+    } else if let Some(rest) = rest.strip_prefix('D') {
+        (false, false, rest)
+    } else {
+        return None;
+    };
+
+    // optional third letter of the currency code
+    if rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        rest = &rest[1..];
+    }
+
+    let end = rest.find(|c: char| !c.is_ascii_digit() && c != ',').unwrap_or(rest.len());
+    let amount = &rest[..end];
+
+    if !amount.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+
+    // transaction type, e.g. NTRF
+    let rest = rest.get(end + 4..)?;
+
+    let (reference, bank_reference) = match rest.find("//") {
+        Some(i) => (&rest[..i], &rest[i + 2..]),
+        None => (rest, ""),
+    };
+
+    Some(Entry {
+        date: format!("20{}-{}-{}", &date[0..2], &date[2..4], &date[4..6]),
+        credit,
+        reversal,
+        // decimal commas, and whole amounts end in one
+        amount: amount.trim_end_matches(',').replace(',', "."),
+        reference: reference.trim().to_string(),
+        bank_reference: bank_reference.trim().to_string(),
+        ..Default::default()
+    })
+}
+
+// find the account references in a :86: field, which is either German structured, with ?NN
+// subfields after a 3 digit business code, SWIFT structured, with /CODE/ subfields, or free text
+fn parse_info(info: &str) -> Info {
+    let mut accounts = Vec::new();
+
+    if info.get(0..3).is_some_and(|code| code.chars().all(|c| c.is_ascii_digit())) && info.contains('?') {
+        // lines are wrapped at a fixed width, not at subfields
+        let joined: String = info.lines().collect();
+
+        for subfield in joined.split('?').skip(1) {
+            // the counterparty's account number or IBAN, never its name in ?32, which isn't unique
+            if subfield.starts_with("31") {
+                accounts.push(subfield.get(2..).unwrap_or("").trim().to_string());
+            }
+        }
+    } else {
+        for code in &["/IBAN/", "/ACCT/"] {
+            if let Some(i) = info.find(code) {
+                accounts.push(info[i + code.len()..].split(&['/', '\n'][..]).next().unwrap_or("").trim().to_string());
+            }
+        }
+    }
+
+    accounts.retain(|account| !account.is_empty());
+
+    Info { accounts }
+}
+
+// import the statement lines of SWIFT MT940 statements, credits become deposits and debits
+// withdrawals
+//
+// the client is found from the counterparty's account in the structured :86: information, falling
+// back to the statement's :25: account, and the tx id is the line's reference for the account
+// owner, or the bank's reference if that is NONREF
+pub fn import_mt940<R: BufRead>(reader: R, clients: &ClientMap) -> Result<Import, Box<dyn Error>> {
+    let mut import = Import::default();
+    let mut statement_account = String::new();
+    let mut currency = String::new();
+    let mut entry: Option<Entry> = None;
+
+    for (name, value) in fields(reader)? {
+        if name == "86" {
+            if let Some(entry) = entry.as_mut() {
+                entry.info = value;
+            }
+
+            continue;
+        }
+
+        if let Some(entry) = entry.take() {
+            map_entry(entry, &statement_account, &currency, clients, &mut import);
+        }
+
+        match name.as_str() {
+            "25" => statement_account = value.trim().to_string(),
+            // opening balance, C or D, YYMMDD, then the currency
+            "60F" | "60M" => currency = value.get(7..10).unwrap_or("").to_string(),
+            "61" => match parse_entry(&value) {
+                Some(parsed) => entry = Some(parsed),
+                None => return Err(format!("Bad MT940 statement line {}", value.lines().next().unwrap_or("")).into()),
+            },
+            _ => (),
+        }
+    }
+
+    if let Some(entry) = entry.take() {
+        map_entry(entry, &statement_account, &currency, clients, &mut import);
+    }
+
+    Ok(import)
+}
+
+// turn a statement line into a tx, or record why it can't be
+fn map_entry(entry: Entry, statement_account: &str, currency: &str, clients: &ClientMap, import: &mut Import) {
+    let info = parse_info(&entry.info);
+
+    let reference = if entry.reference.is_empty() || entry.reference == NONREF {
+        &entry.bank_reference
+    } else {
+        &entry.reference
+    };
+
+    let counterparty = info.accounts.first().map(|account| account.as_str()).unwrap_or(statement_account);
+
+    if entry.reversal {
+        return import.unmapped(reference, counterparty, "reversal entry");
+    }
+
+    // :25: is sometimes the bank code and account number, e.g. 37040044/0532013000
+    let local_account = statement_account.rsplit('/').next().unwrap_or("");
+
+    // free text is never matched, a word of it could be anyone's reference
+    let mapped = info.accounts.iter()
+        .map(|account| account.as_str())
+        .chain(vec![statement_account, local_account])
+        .filter(|account| !account.is_empty())
+        .find_map(|account| clients.get(account).map(|client| (account, client)));
+
+    let (account, client) = match mapped {
+        Some(mapped) => mapped,
+        None => return import.unmapped(reference, counterparty, "no client for account"),
+    };
+
+    let tx: TxId = match reference.parse() {
+        Ok(tx) => tx,
+        Err(_) => return import.unmapped(reference, account, "entry reference is not a tx id"),
+    };
+
+    import.transactions.push(Transaction {
+        tx_type: if entry.credit { DEPOSIT } else { WITHDRAWAL }.to_string(),
+        client,
+        tx,
+        amount: entry.amount,
+        currency: currency.to_string(),
+        timestamp: parse_date(&entry.date),
+        imported: true,
+        ..Default::default()
+    });
+}
@@ -0,0 +1,289 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::{BufRead, Read, Write};
+
+use crate::ids::{tx_id_from, ClientId, TxId, WIDE_IDS};
+use crate::import::{parse_date, ClientMap, Import};
+use crate::payments::{Transaction, DEPOSIT, RETURNED, WITHDRAWAL};
+
+// every NACHA record is this many characters
+pub const RECORD_LEN: usize = 94;
+
+// an entry detail record, with its return addenda if it has one
+struct Entry {
+    code: String,
+    account: String,
+    amount: String,
+    individual_id: String,
+    trace: String,
+    timestamp: Option<u64>,
+    // (reason code, original trace number) of a return
+    returned: Option<(String, String)>,
+    // an addenda type which isn't imported, like a notification of change
+    other_addenda: Option<String>,
+}
+
+// the tx id of a trace number, all 15 digits of it with wide or uuid ids, or else just the sequence
+// number in the last 7, which is only unique within the originating bank, see trace_source
+pub fn trace_tx_id(trace: &str) -> Result<TxId, &'static str> {
+    let trace = trace.trim();
+
+    if trace.len() != 15 || !trace.chars().all(|c| c.is_ascii_digit()) {
+        return Err("bad trace number");
+    }
+
+    let number: u64 = trace.parse().map_err(|_| "bad trace number")?;
+    let number = if WIDE_IDS { number } else { number % 10_000_000 };
+
+    tx_id_from(number).ok_or("trace number doesn't fit in a tx id")
+}
+
+// the source of a trace number's tx, the routing number of the bank which originated it in its
+// first 8 digits, so with per source ids the tx id only has to be unique within that bank
+pub fn trace_source(trace: &str) -> String {
+    trace.trim().get(..8).unwrap_or("").to_string()
+}
+
+// the withdrawal a NACHA payout entry was written for
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct PayoutTrace {
+    pub trace: String,
+    pub client: ClientId,
+    pub tx: TxId,
+    // the source of the withdrawal, for per source ids
+    pub source: Option<String>,
+}
+
+// the trace numbers of the NACHA payouts written so far, so returns of them can be matched to
+// their withdrawals and no trace number is ever written twice
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TraceMap {
+    traces: BTreeMap<String, PayoutTrace>,
+}
+
+impl TraceMap {
+    // ctor
+    pub fn new() -> TraceMap {
+        Default::default()
+    }
+
+    // load a trace map from CSV with trace,client,tx,source columns
+    pub fn load<R: Read>(reader: R) -> Result<TraceMap, Box<dyn Error>> {
+        let mut csv_reader = csv::Reader::from_reader(reader);
+        let mut traces = TraceMap::new();
+
+        for result in csv_reader.deserialize() {
+            traces.insert(result?);
+        }
+
+        Ok(traces)
+    }
+
+    // write the trace map as CSV, in trace number order
+    pub fn write<W: Write>(&self, writer: W) -> Result<(), Box<dyn Error>> {
+        let mut csv_writer = csv::Writer::from_writer(writer);
+
+        for trace in self.traces.values() {
+            csv_writer.serialize(trace)?;
+        }
+
+        csv_writer.flush()?;
+
+        Ok(())
+    }
+
+    // record the passed payout's trace number, replacing any earlier one of the same number
+    pub fn insert(&mut self, trace: PayoutTrace) {
+        self.traces.insert(trace.trace.trim().to_string(), trace);
+    }
+
+    // the payout the passed trace number was written for
+    pub fn get(&self, trace: &str) -> Option<&PayoutTrace> {
+        self.traces.get(trace.trim())
+    }
+
+    // the sequence number after the last one the originating bank with the passed 8 digit routing
+    // number has been used with
+    pub fn next_sequence(&self, odfi: &str) -> u64 {
+        self.traces.keys()
+            .filter_map(|trace| trace.strip_prefix(odfi))
+            .filter_map(|sequence| sequence.parse::<u64>().ok())
+            .max()
+            .unwrap_or(0) + 1
+    }
+}
+
+// the characters at the passed 1 based, inclusive positions of a record
+fn field(record: &str, from: usize, to: usize) -> &str {
+    record.get(from - 1..to).unwrap_or("").trim()
+}
+
+// import the entries of a NACHA ACH file, as the originator sees them, so credits to the client's
+// bank account become withdrawals and debits deposits, as with the payouts in payout.rs
+//
+// the client is found from the entry's DFI account number, falling back to its individual id,
+// and the tx id and source are those of the entry's trace number, see trace_tx_id.  returns,
+// entries with a type 99 addenda, become a returned tx of the original entry's tx, or of the
+// withdrawal it paid out if traces has its trace number.  the ledger puts a returned withdrawal's
+// funds back, and disputes and charges back a returned deposit if it has already settled
+pub fn import_nacha<R: BufRead>(reader: R, clients: &ClientMap, traces: &TraceMap) -> Result<Import, Box<dyn Error>> {
+    let mut import = Import::default();
+    let mut entry: Option<Entry> = None;
+    let mut timestamp = None;
+    let mut number = 0;
+
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim_end_matches(&['\r', '\n'][..]);
+
+        // some banks send the whole file as one line
+        let records = line.as_bytes().chunks(RECORD_LEN).map(|chunk| String::from_utf8_lossy(chunk).into_owned());
+
+        for record in records {
+            number += 1;
+
+            if record.trim().is_empty() {
+                continue;
+            }
+
+            // the fields are sliced by position, which only works on single byte characters
+            if !record.is_ascii() {
+                return Err(format!("NACHA record {} has characters which aren't ASCII", number).into());
+            }
+
+            if record.len() != RECORD_LEN {
+                return Err(format!("NACHA record {} is {} characters, not {}", number, record.len(), RECORD_LEN).into());
+            }
+
+            // addenda belong to the entry before them, anything else ends it
+            if !record.starts_with('7') {
+                if let Some(entry) = entry.take() {
+                    map_entry(entry, clients, traces, &mut import);
+                }
+            }
+
+            match &record[0..1] {
+                "5" => {
+                    // effective entry date, YYMMDD
+                    let date = field(&record, 70, 75);
+                    timestamp = if date.len() == 6 {
+                        parse_date(&format!("20{}-{}-{}", &date[0..2], &date[2..4], &date[4..6]))
+                    } else {
+                        None
+                    };
+                },
+                "6" => {
+                    entry = Some(Entry {
+                        code: field(&record, 2, 3).to_string(),
+                        account: field(&record, 13, 29).to_string(),
+                        amount: field(&record, 30, 39).to_string(),
+                        individual_id: field(&record, 40, 54).to_string(),
+                        trace: field(&record, 80, 94).to_string(),
+                        timestamp,
+                        returned: None,
+                        other_addenda: None,
+                    });
+                },
+                "7" => {
+                    let entry = match entry.as_mut() {
+                        Some(entry) => entry,
+                        None => return Err(format!("NACHA addenda record {} has no entry", number).into()),
+                    };
+
+                    let addenda_type = field(&record, 2, 3);
+
+                    if addenda_type == "99" {
+                        entry.returned = Some((field(&record, 4, 6).to_string(), field(&record, 7, 21).to_string()));
+                    } else if addenda_type != "05" {
+                        entry.other_addenda = Some(addenda_type.to_string());
+                    }
+                },
+                "1" | "8" | "9" => (),
+                other => return Err(format!("NACHA record {} has unknown type {}", number, other).into()),
+            }
+        }
+    }
+
+    if let Some(entry) = entry.take() {
+        map_entry(entry, clients, traces, &mut import);
+    }
+
+    Ok(import)
+}
+
+// turn an entry detail record into txs, or record why it can't be
+fn map_entry(entry: Entry, clients: &ClientMap, traces: &TraceMap, import: &mut Import) {
+    // a returned payout belongs to the client it was written for, whatever account it was paid to
+    if let Some((reason, Some(payout))) = entry.returned.as_ref().map(|(reason, original)| (reason, traces.get(original))) {
+        return import.transactions.push(Transaction {
+            tx_type: RETURNED.to_string(),
+            client: payout.client,
+            tx: payout.tx,
+            source: payout.source.clone(),
+            reason: reason.clone(),
+            timestamp: entry.timestamp,
+            imported: true,
+            ..Default::default()
+        });
+    }
+
+    let client = match clients.get(&entry.account).or_else(|| clients.get(&entry.individual_id)) {
+        Some(client) => client,
+        None => return import.unmapped(&entry.trace, &entry.account, "no client for account"),
+    };
+
+    if let Some(addenda_type) = &entry.other_addenda {
+        let reason = if addenda_type == "98" { "notification of change".to_string() } else { format!("addenda type {}", addenda_type) };
+        return import.unmapped(&entry.trace, &entry.account, &reason);
+    }
+
+    // a return reverses the original entry, whatever its own transaction code
+    if let Some((reason, original)) = &entry.returned {
+        let tx = match trace_tx_id(original) {
+            Ok(tx) => tx,
+            Err(reason) => return import.unmapped(&entry.trace, &entry.account, &format!("{} of the original entry", reason)),
+        };
+
+        return import.transactions.push(Transaction {
+            tx_type: RETURNED.to_string(),
+            client,
+            tx,
+            source: Some(trace_source(original)),
+            reason: reason.clone(),
+            timestamp: entry.timestamp,
+            imported: true,
+            ..Default::default()
+        });
+    }
+
+    let tx_type = match entry.code.as_str() {
+        // credits pay the client out, debits collect from them
+        "22" | "32" => WITHDRAWAL,
+        "27" | "37" => DEPOSIT,
+        "23" | "28" | "33" | "38" => return import.unmapped(&entry.trace, &entry.account, "prenote"),
+        _ => return import.unmapped(&entry.trace, &entry.account, "unknown transaction code"),
+    };
+
+    let cents: i64 = match entry.amount.parse() {
+        Ok(cents) => cents,
+        Err(_) => return import.unmapped(&entry.trace, &entry.account, "bad amount"),
+    };
+
+    let tx = match trace_tx_id(&entry.trace) {
+        Ok(tx) => tx,
+        Err(reason) => return import.unmapped(&entry.trace, &entry.account, reason),
+    };
+
+    import.transactions.push(Transaction {
+        tx_type: tx_type.to_string(),
+        client,
+        tx,
+        source: Some(trace_source(&entry.trace)),
+        amount: Decimal::new(cents, 2).to_string(),
+        timestamp: entry.timestamp,
+        imported: true,
+        ..Default::default()
+    });
+}
@@ -0,0 +1,280 @@
+use rust_decimal::prelude::RoundingStrategy;
+use rust_decimal::Decimal;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::error::Error;
+use std::io::Write;
+use std::str::FromStr;
+
+use crate::payments::{Account, AccountStatus, Balance, PaymentError};
+
+// how amounts are rounded to the output scale
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rounding {
+    // half to even
+    Bankers,
+    // half away from zero, the same as Decimal::rescale
+    #[default]
+    HalfUp,
+    // towards zero
+    Truncate,
+}
+
+impl FromStr for Rounding {
+    type Err = String;
+
+    // parse "bankers", "half-up" or "truncate"
+    fn from_str(s: &str) -> Result<Rounding, String> {
+        match s {
+            "bankers" => Ok(Rounding::Bankers),
+            "half-up" => Ok(Rounding::HalfUp),
+            "truncate" => Ok(Rounding::Truncate),
+            _ => Err(format!("Bad rounding {}", s)),
+        }
+    }
+}
+
+// how amounts are written to the output
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Precision {
+    // number of decimal places
+    pub scale: u32,
+    pub rounding: Rounding,
+    // refuse to round an amount which doesn't fit the scale, rather than change it
+    pub strict: bool,
+}
+
+impl Default for Precision {
+    fn default() -> Precision {
+        Precision {
+            scale: 4,
+            rounding: Rounding::HalfUp,
+            strict: false,
+        }
+    }
+}
+
+impl Precision {
+    // round the passed amount to the output scale
+    pub fn round(&self, amount: Decimal) -> Result<Decimal, PaymentError> {
+        let strategy = match self.rounding {
+            Rounding::Bankers => RoundingStrategy::MidpointNearestEven,
+            Rounding::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            Rounding::Truncate => RoundingStrategy::ToZero,
+        };
+
+        let mut rounded = amount.round_dp_with_strategy(self.scale, strategy);
+
+        if self.strict && rounded != amount {
+            return Err(PaymentError::InexactAmount);
+        }
+
+        // pad to the full scale for uniform output
+        rounded.rescale(self.scale);
+
+        Ok(rounded)
+    }
+}
+
+// a client account's balance in one currency, as it is written to the output
+pub struct AccountRecord<'a> {
+    account: &'a Account,
+    currency: &'a str,
+    balance: Balance,
+    // include the lock_reason and locked_by_tx columns
+    provenance: bool,
+}
+
+impl<'a> AccountRecord<'a> {
+    // ctor
+    pub fn new(account: &'a Account, currency: &'a str, provenance: bool) -> AccountRecord<'a> {
+        AccountRecord {
+            account,
+            currency,
+            balance: account.balance(currency),
+            provenance,
+        }
+    }
+
+    // one record per currency the account holds, or a single empty one if it holds none
+    pub fn all(account: &'a Account, provenance: bool) -> Vec<AccountRecord<'a>> {
+        if account.balances.is_empty() {
+            return vec![AccountRecord::new(account, "", provenance)];
+        }
+
+        account.balances.keys().map(|currency| AccountRecord::new(account, currency, provenance)).collect()
+    }
+
+    // the same records as all, with the balances rounded to the passed precision, and none if any
+    // amount can't be
+    pub fn rounded(account: &'a Account, precision: &Precision, provenance: bool) -> Result<Vec<AccountRecord<'a>>, PaymentError> {
+        let mut balances = account.round(precision)?;
+
+        // the empty record of an account without balances is written at the same scale
+        if balances.is_empty() {
+            let balance = Balance::default().round(precision)?;
+            return Ok(vec![AccountRecord { account, currency: "", balance, provenance }]);
+        }
+
+        Ok(account.balances.keys()
+            .map(|currency| AccountRecord {
+                account,
+                currency,
+                balance: balances.remove(currency).unwrap_or_default(),
+                provenance,
+            })
+            .collect())
+    }
+}
+
+impl<'a> Serialize for AccountRecord<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let account = self.account;
+        let balance = &self.balance;
+        let len = if self.provenance { 12 } else { 10 };
+
+        let mut record = serializer.serialize_struct("Account", len)?;
+        record.serialize_field("client", &account.client)?;
+        record.serialize_field("currency", self.currency)?;
+        record.serialize_field("available", &balance.available)?;
+        record.serialize_field("held", &balance.held)?;
+        record.serialize_field("pending", &balance.pending)?;
+        record.serialize_field("total", &balance.total)?;
+        // kept for consumers from before there were statuses, which saw every account that rejects
+        // deposits and withdrawals as locked, including one frozen by a withdrawal dispute
+        record.serialize_field("locked", &(account.status >= AccountStatus::Frozen))?;
+        record.serialize_field("status", &account.status)?;
+        record.serialize_field("reason", &account.reason)?;
+        record.serialize_field("admin", &account.admin)?;
+
+        if self.provenance {
+            let event = account.locked_by();
+            record.serialize_field("lock_reason", &event.map(|e| &e.kind))?;
+            record.serialize_field("locked_by_tx", &event.map(|e| e.tx))?;
+        }
+
+        record.end()
+    }
+}
+
+// the formats accounts can be written in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Csv,
+    // a single JSON array of accounts
+    Json,
+    // one JSON object per line
+    JsonLines,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    // parse "csv", "json" or "jsonl"
+    fn from_str(s: &str) -> Result<OutputFormat, String> {
+        match s {
+            "csv" => Ok(OutputFormat::Csv),
+            "json" => Ok(OutputFormat::Json),
+            "jsonl" => Ok(OutputFormat::JsonLines),
+            _ => Err(format!("Bad output format {}", s)),
+        }
+    }
+}
+
+impl OutputFormat {
+    // a writer of this format to the passed output
+    pub fn writer<'a, W: Write + 'a>(self, output: W) -> Box<dyn AccountWriter + 'a> {
+        match self {
+            OutputFormat::Csv => Box::new(CsvAccountWriter::new(output)),
+            OutputFormat::Json => Box::new(JsonAccountWriter::new(output)),
+            OutputFormat::JsonLines => Box::new(JsonLinesAccountWriter::new(output)),
+        }
+    }
+}
+
+// writes account records in one of the output formats, with the same field names in all of them
+pub trait AccountWriter {
+    fn write(&mut self, record: &AccountRecord) -> Result<(), Box<dyn Error>>;
+
+    // complete the output, nothing may be written after this
+    fn finish(&mut self) -> Result<(), Box<dyn Error>>;
+}
+
+// writes accounts as CSV, with a header row
+pub struct CsvAccountWriter<W: Write> {
+    writer: csv::Writer<W>,
+}
+
+impl<W: Write> CsvAccountWriter<W> {
+    // ctor
+    pub fn new(output: W) -> CsvAccountWriter<W> {
+        CsvAccountWriter { writer: csv::Writer::from_writer(output) }
+    }
+}
+
+impl<W: Write> AccountWriter for CsvAccountWriter<W> {
+    fn write(&mut self, record: &AccountRecord) -> Result<(), Box<dyn Error>> {
+        Ok(self.writer.serialize(record)?)
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+// writes accounts as a JSON array, amounts are strings so no precision is lost
+pub struct JsonAccountWriter<W: Write> {
+    output: W,
+    count: usize,
+}
+
+impl<W: Write> JsonAccountWriter<W> {
+    // ctor
+    pub fn new(output: W) -> JsonAccountWriter<W> {
+        JsonAccountWriter { output, count: 0 }
+    }
+}
+
+impl<W: Write> AccountWriter for JsonAccountWriter<W> {
+    fn write(&mut self, record: &AccountRecord) -> Result<(), Box<dyn Error>> {
+        let separator = if self.count == 0 { "[" } else { "," };
+        write!(self.output, "{}\n  {}", separator, serde_json::to_string(record)?)?;
+        self.count += 1;
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.count == 0 {
+            writeln!(self.output, "[]")?;
+        } else {
+            writeln!(self.output, "\n]")?;
+        }
+
+        Ok(self.output.flush()?)
+    }
+}
+
+// writes accounts as JSON Lines, one object per line
+pub struct JsonLinesAccountWriter<W: Write> {
+    output: W,
+}
+
+impl<W: Write> JsonLinesAccountWriter<W> {
+    // ctor
+    pub fn new(output: W) -> JsonLinesAccountWriter<W> {
+        JsonLinesAccountWriter { output }
+    }
+}
+
+impl<W: Write> AccountWriter for JsonLinesAccountWriter<W> {
+    fn write(&mut self, record: &AccountRecord) -> Result<(), Box<dyn Error>> {
+        writeln!(self.output, "{}", serde_json::to_string(record)?)?;
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(self.output.flush()?)
+    }
+}
@@ -1,11 +1,20 @@
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 use serde::{Serialize, Deserialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::error::Error;
 use std::fmt;
 use std::str::FromStr;
 
+use crate::alerts::{Alert, AlertConfig, Monitor};
+use crate::fx::RateTable;
+use crate::output::Precision;
+use crate::payout::Payout;
+use crate::ids::{ClientId, TxId};
+use crate::risk::{RiskEvent, RiskRule, RiskStats};
+use crate::rules::{History, Rule, RuleHit};
+use crate::validate::{check_duplicate, check_funds, check_schema, check_status, owns_id, Validated};
+
 // string constants for tx type
 pub const WITHDRAWAL: &str = "withdrawal";
 pub const DEPOSIT: &str = "deposit";
@@ -13,18 +22,67 @@ pub const DISPUTE: &str = "dispute";
 pub const RESOLVE: &str = "resolve";
 pub const CHARGEBACK: &str = "chargeback";
 
+// two phase tx types, an authorization holds funds until it is captured or voided
+pub const AUTHORIZE: &str = "authorize";
+pub const CAPTURE: &str = "capture";
+pub const VOID: &str = "void";
+
+// settlement tx types, both refer to a pending deposit by its tx id
+pub const SETTLE: &str = "settle";
+pub const RETURNED: &str = "returned";
+
+// converts funds between two of a client's currencies, priced from the rate table
+pub const EXCHANGE: &str = "exchange";
+
+// administrative tx types, these bypass the account status checks and are only accepted from an
+// admin input
+pub const UNLOCK: &str = "unlock";
+pub const FREEZE: &str = "freeze";
+pub const ADJUST: &str = "adjust";
+pub const CLOSE: &str = "close";
+
+// true if the passed tx type is an administrative one
+pub fn is_admin(tx_type: &str) -> bool {
+    tx_type == UNLOCK || tx_type == FREEZE || tx_type == ADJUST || tx_type == CLOSE
+}
+
 // the set of errors which can happen during
 #[derive(Debug)]
 pub enum PaymentError {
+    AccountUnderReview,
+    AccountFrozen,
     AccountLocked,
+    AccountClosed,
+    InvalidStatusTransition,
     BadDecimal,
+    NonPositiveAmount,
     UnknownTxType,
     InsufficientFunds,
-    DuplicateTransaction,
+    DuplicateTransaction { stored: Box<Transaction>, incoming: Box<Transaction> },
     AlreadyDisputed,
     NotDisputed,
     DisputedWrongClient,
     DisputedTxNotFound,
+    NotDisputable,
+    MissingReason,
+    AuthorizationNotFound,
+    AuthorizationWrongClient,
+    CaptureExceedsAuthorization,
+    DepositNotPending,
+    DepositNotSettled,
+    PendingWrongClient,
+    ReturnedWrongClient,
+    AlreadyReturned,
+    ReturnNotImported,
+    LimitExceeded { rule: String },
+    TransactionIdBurned,
+    CurrencyNotHeld,
+    MissingCurrency,
+    RateNotFound,
+    HouseAccountMissing,
+    HouseAccountClosed,
+    InexactAmount,
+    AdminOnly,
 }
 
 impl fmt::Display for PaymentError {
@@ -36,113 +94,769 @@ impl fmt::Display for PaymentError {
 impl Error for PaymentError {
     fn description(&self) -> &str {
         match self {
+            PaymentError::AccountUnderReview => "AccountUnderReview",
+            PaymentError::AccountFrozen => "AccountFrozen",
             PaymentError::AccountLocked => "AccountLocked",
+            PaymentError::AccountClosed => "AccountClosed",
+            PaymentError::InvalidStatusTransition => "InvalidStatusTransition",
             PaymentError::BadDecimal => "BadDecimal",
+            PaymentError::NonPositiveAmount => "NonPositiveAmount",
             PaymentError::UnknownTxType => "UnknownTxType",
             PaymentError::InsufficientFunds => "InsufficientFunds",
             PaymentError::AlreadyDisputed => "AlreadyDisputed",
             PaymentError::NotDisputed => "NotDisputed",
-            PaymentError::DuplicateTransaction => "DuplicateTransaction",
+            PaymentError::DuplicateTransaction { .. } => "DuplicateTransaction",
             PaymentError::DisputedWrongClient => "DisputedWrongClient",
             PaymentError::DisputedTxNotFound => "DisputedTxNotFound",
+            PaymentError::NotDisputable => "NotDisputable",
+            PaymentError::MissingReason => "MissingReason",
+            PaymentError::AuthorizationNotFound => "AuthorizationNotFound",
+            PaymentError::AuthorizationWrongClient => "AuthorizationWrongClient",
+            PaymentError::CaptureExceedsAuthorization => "CaptureExceedsAuthorization",
+            PaymentError::DepositNotPending => "DepositNotPending",
+            PaymentError::DepositNotSettled => "DepositNotSettled",
+            PaymentError::PendingWrongClient => "PendingWrongClient",
+            PaymentError::ReturnedWrongClient => "ReturnedWrongClient",
+            PaymentError::AlreadyReturned => "AlreadyReturned",
+            PaymentError::ReturnNotImported => "ReturnNotImported",
+            PaymentError::LimitExceeded { .. } => "LimitExceeded",
+            PaymentError::TransactionIdBurned => "TransactionIdBurned",
+            PaymentError::CurrencyNotHeld => "CurrencyNotHeld",
+            PaymentError::MissingCurrency => "MissingCurrency",
+            PaymentError::RateNotFound => "RateNotFound",
+            PaymentError::HouseAccountMissing => "HouseAccountMissing",
+            PaymentError::HouseAccountClosed => "HouseAccountClosed",
+            PaymentError::InexactAmount => "InexactAmount",
+            PaymentError::AdminOnly => "AdminOnly",
         }
     }
 }
 
 // a client transaction, deserialized from input
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize)]
 pub struct Transaction {
     #[serde(rename = "type")]
     pub tx_type: String,
-    pub client: u16,
-    pub tx: u32,
+    pub client: ClientId,
+    pub tx: TxId,
     #[serde(default)]
     pub amount: String,
+    // reason code, mandatory for adjustments
+    #[serde(default)]
+    pub reason: String,
+    // seconds since the unix epoch, only needed for time based settlement
+    #[serde(default)]
+    pub timestamp: Option<u64>,
+    // the file or partner the tx came from, only needed for per source ids
+    #[serde(default)]
+    pub source: Option<String>,
+    // currency code of the amount, empty if the input has no currencies
+    #[serde(default)]
+    pub currency: String,
+    // currency code an exchange converts into
+    #[serde(default)]
+    pub to_currency: String,
+    // set by the bank file importers, whose withdrawals the bank has already paid out
+    #[serde(skip)]
+    pub imported: bool,
+    // set for txs read from an admin input, which are the only ones allowed an admin tx type
+    #[serde(skip)]
+    pub admin: bool,
 }
 
-// current state of a client account, will be serialized as output
-#[derive(Debug, Serialize)]
-pub struct Account {
-    pub client: u16,
+// how a tx which didn't fail was handled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Applied,
+    // an exact replay of a tx which was applied before, so nothing was done
+    AlreadyApplied,
+}
+
+// lifecycle state of an account, ordered from least to most restrictive
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountStatus {
+    // everything is accepted
+    #[default]
+    Active,
+    // no funds may leave the account
+    UnderReview,
+    // pending review of a disputed withdrawal, only disputes and admin txs are accepted
+    Frozen,
+    // after a chargeback, only disputes and admin txs are accepted
+    Locked,
+    // terminal, nothing is accepted
+    Closed,
+}
+
+impl AccountStatus {
+    // true if an account in this status may move to the passed status
+    pub fn can_transition(self, to: AccountStatus) -> bool {
+        if self == to {
+            return self != AccountStatus::Closed;
+        }
+
+        match self {
+            AccountStatus::Active | AccountStatus::UnderReview | AccountStatus::Frozen => true,
+            AccountStatus::Locked => to == AccountStatus::Active || to == AccountStatus::Closed,
+            AccountStatus::Closed => false,
+        }
+    }
+
+    // true if an account in this status accepts the passed tx type
+    pub fn accepts(self, tx_type: &str) -> bool {
+        match self {
+            AccountStatus::Active => true,
+            AccountStatus::UnderReview => {
+                tx_type != WITHDRAWAL && tx_type != AUTHORIZE && tx_type != CAPTURE && tx_type != EXCHANGE
+            },
+            AccountStatus::Frozen | AccountStatus::Locked => {
+                tx_type == DISPUTE || tx_type == RESOLVE || tx_type == CHARGEBACK || tx_type == VOID
+                    || tx_type == SETTLE || tx_type == RETURNED || is_admin(tx_type)
+            },
+            AccountStatus::Closed => false,
+        }
+    }
+
+    // the error returned for a tx which this status does not accept
+    pub fn rejection(self) -> PaymentError {
+        match self {
+            AccountStatus::Active => PaymentError::InvalidStatusTransition,
+            AccountStatus::UnderReview => PaymentError::AccountUnderReview,
+            AccountStatus::Frozen => PaymentError::AccountFrozen,
+            AccountStatus::Locked => PaymentError::AccountLocked,
+            AccountStatus::Closed => PaymentError::AccountClosed,
+        }
+    }
+}
+
+// the funds a client holds in one currency
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Balance {
     pub available: Decimal,
     pub held: Decimal,
+    // deposits which have not settled yet, included in total but not available
+    pub pending: Decimal,
     pub total: Decimal,
-    pub locked: bool,
+}
+
+impl Default for Balance {
+    fn default() -> Balance {
+        Balance {
+            available: dec!(0.0),
+            held: dec!(0.0),
+            pending: dec!(0.0),
+            total: dec!(0.0),
+        }
+    }
+}
+
+impl Balance {
+    // rescale all the decimal vars for uniform output
+    pub fn rescale(&mut self, scale: u32) {
+        self.available.rescale(scale);
+        self.held.rescale(scale);
+        self.pending.rescale(scale);
+        self.total.rescale(scale);
+    }
+
+    // round all the decimal vars to the passed output precision
+    pub fn round(&self, precision: &Precision) -> Result<Balance, PaymentError> {
+        Ok(Balance {
+            available: precision.round(self.available)?,
+            held: precision.round(self.held)?,
+            pending: precision.round(self.pending)?,
+            total: precision.round(self.total)?,
+        })
+    }
+}
+
+// current state of a client account
+#[derive(Debug)]
+pub struct Account {
+    pub client: ClientId,
+    // balances by currency code, the empty code for txs without a currency
+    pub balances: BTreeMap<String, Balance>,
+    pub status: AccountStatus,
+    // why the account is in its current status, empty when active
+    pub reason: String,
+    // set once any administrative tx has been applied to the account
+    pub admin: bool,
+    // the txs which restricted the account, oldest first, cleared once active again
+    pub lock_events: Vec<LockEvent>,
+    // dispute activity by currency code, for the risk rules
+    pub risk_stats: BTreeMap<String, RiskStats>,
+    // disputes of withdrawals which haven't been resolved or charged back yet
+    pub withdrawal_disputes: u32,
+    // set if a dispute of a withdrawal froze the account, rather than an admin or a risk rule, so
+    // resolving the last open one unfreezes it
+    pub dispute_freeze: bool,
+}
+
+// a tx which moved an account away from active, or kept it restricted
+#[derive(Clone, Debug, PartialEq)]
+pub struct LockEvent {
+    pub tx: TxId,
+    pub kind: String,
+    // position of the tx in the ledger's input, starting at 1
+    pub ordinal: u64,
+}
+
+// when deposited funds become available
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Settlement {
+    // deposits are available straight away
+    #[default]
+    Immediate,
+    // deposits settle once this many more txs have been processed
+    Transactions(u64),
+    // deposits settle once a tx timestamped this many seconds later has been processed
+    Seconds(u64),
+    // deposits only settle on a settle tx
+    Explicit,
+}
+
+impl FromStr for Settlement {
+    type Err = String;
+
+    // parse "immediate", "explicit", or a delay like "5tx" or "3600s"
+    fn from_str(s: &str) -> Result<Settlement, String> {
+        let delay = |suffix: &str| s.strip_suffix(suffix).and_then(|n| n.parse().ok());
+
+        if s == "immediate" {
+            Ok(Settlement::Immediate)
+        } else if s == "explicit" {
+            Ok(Settlement::Explicit)
+        } else if let Some(n) = delay("tx") {
+            Ok(Settlement::Transactions(n))
+        } else if let Some(n) = delay("s") {
+            Ok(Settlement::Seconds(n))
+        } else {
+            Err(format!("Bad settlement {}", s))
+        }
+    }
+}
+
+// how widely tx ids must be unique, and so which txs a dispute can refer to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IdScope {
+    // ids are unique across all clients
+    #[default]
+    Global,
+    // each client numbers its own txs
+    Client,
+    // each source file or partner numbers its own txs
+    Source,
+}
+
+impl FromStr for IdScope {
+    type Err = String;
+
+    // parse "global", "client" or "source"
+    fn from_str(s: &str) -> Result<IdScope, String> {
+        match s {
+            "global" => Ok(IdScope::Global),
+            "client" => Ok(IdScope::Client),
+            "source" => Ok(IdScope::Source),
+            _ => Err(format!("Bad id scope {}", s)),
+        }
+    }
+}
+
+// a tx id qualified by its scope, which txs are stored under
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TxKey {
+    Global(TxId),
+    Client(ClientId, TxId),
+    Source(Option<String>, TxId),
+}
+
+// ledger settings, the defaults match the behaviour of a plain ledger
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    // number of txs after which an uncaptured authorization is released, never if None
+    pub authorization_ttl: Option<u64>,
+    pub settlement: Settlement,
+    // limits on withdrawals and authorizations, checked in order
+    pub rules: Vec<Rule>,
+    // thresholds of dispute activity which restrict an account, checked in order
+    pub risk: Vec<RiskRule>,
+    pub alerts: AlertConfig,
+    // reserve the ids of rejected txs, so they can never be used
+    pub burn_rejected_ids: bool,
+    pub id_scope: IdScope,
+    // rates which exchanges are priced from
+    pub rates: RateTable,
+    // the client credited with the spread of exchanges, which are rejected if None and they have one
+    pub house_account: Option<ClientId>,
+}
+
+// funds held for an authorization until it is captured, voided or expires
+#[derive(Clone, Debug)]
+struct Authorization {
+    client: ClientId,
+    currency: String,
+    amount: Decimal,
+}
+
+// a deposit whose funds are still pending
+#[derive(Clone, Debug)]
+struct PendingDeposit {
+    client: ClientId,
+    currency: String,
+    amount: Decimal,
 }
 
 // global data for all transactions/disputes
 #[derive(Debug, Default)]
 pub struct GlobalData {
-    txs: HashMap<u32, Transaction>,
-    disputes: HashSet<u32>,
+    config: Config,
+    txs: HashMap<TxKey, Transaction>,
+    disputes: HashSet<TxKey>,
+    authorizations: HashMap<TxKey, Authorization>,
+    // (seq, tx) of authorizations in order of expiry
+    expiries: VecDeque<(u64, TxKey)>,
+    pending: HashMap<TxKey, PendingDeposit>,
+    // (seq or time, tx) of pending deposits in order of settlement
+    settlements: VecDeque<(u64, TxKey)>,
+    // number of txs passed to the ledger so far
+    seq: u64,
+    // latest timestamp seen so far
+    now: u64,
+    // recent outflows, for the velocity rules
+    history: History,
+    // recent deposits, for the compliance alerts
+    monitor: Monitor,
+    // ids of rejected txs, if they are burned
+    burned: HashSet<TxKey>,
+    // deposits and withdrawals reversed by a returned tx, which can't be disputed or returned again
+    returned: HashSet<TxKey>,
+}
+
+impl GlobalData {
+    // the key the passed tx, or the one it refers to, is stored under
+    fn key(&self, tx: &Transaction) -> TxKey {
+        self.scoped(tx.client, tx.tx, &tx.source)
+    }
+
+    // the key a tx with the passed client, id and source is stored under
+    fn scoped(&self, client: ClientId, tx: TxId, source: &Option<String>) -> TxKey {
+        match self.config.id_scope {
+            IdScope::Global => TxKey::Global(tx),
+            IdScope::Client => TxKey::Client(client, tx),
+            IdScope::Source => TxKey::Source(source.clone(), tx),
+        }
+    }
+
+    // reserve the id of a rejected tx, if configured to
+    fn burn(&mut self, tx: &Transaction) {
+        let key = self.key(tx);
+
+        if self.config.burn_rejected_ids && owns_id(&tx.tx_type) && !self.txs.contains_key(&key) {
+            self.burned.insert(key);
+        }
+    }
 }
 
 // ledger containing all client accounts
 #[derive(Debug, Default)]
 pub struct Ledger {
-    pub accounts: HashMap<u16, Account>,
+    pub accounts: HashMap<ClientId, Account>,
+    // txs rejected by a rule, and which one
+    pub rule_hits: Vec<RuleHit>,
+    // accounts restricted by a risk rule, and why
+    pub risk_events: Vec<RiskEvent>,
+    // compliance alerts raised by accepted txs
+    pub alerts: Vec<Alert>,
+    // accepted withdrawals, in order, to be paid out
+    pub payouts: Vec<Payout>,
     global: GlobalData,
 }
 
 impl Ledger {
+    // ctor
+    pub fn new(config: Config) -> Ledger {
+        Ledger {
+            accounts: HashMap::new(),
+            rule_hits: Vec::new(),
+            risk_events: Vec::new(),
+            alerts: Vec::new(),
+            payouts: Vec::new(),
+            global: GlobalData {
+                config,
+                ..Default::default()
+            },
+        }
+    }
+
     // find the linked client account and process the passed transaction
-    pub fn process(&mut self, tx: &Transaction) -> Result<(), PaymentError> {
+    pub fn process(&mut self, tx: &Transaction) -> Result<Outcome, PaymentError> {
+        self.global.seq += 1;
+        self.global.now = self.global.now.max(tx.timestamp.unwrap_or(0));
+        self.expire_authorizations();
+        self.settle_deposits();
+
+        let key = self.global.key(tx);
+        let withdrawal = self.global.txs.get(&key).is_some_and(|stored| stored.tx_type == WITHDRAWAL);
+
+        let result = if tx.tx_type == RETURNED && tx.imported && !withdrawal && !self.global.pending.contains_key(&key) {
+            self.return_settled(tx)
+        } else {
+            self.run(tx)
+        };
+
+        if result.is_err() {
+            self.global.burn(tx);
+        }
+
+        result
+    }
+
+    // the sum of the withdrawals from the input which have to be paid out, taken from the stored txs
+    // rather than the payouts, so the payout files' control totals can be checked against it
+    pub fn payout_total(&self) -> Decimal {
+        self.global.txs.iter()
+            .filter(|(key, tx)| tx.tx_type == WITHDRAWAL && !tx.imported && !self.global.returned.contains(key))
+            .map(|(_, tx)| tx)
+            .filter_map(|tx| Decimal::from_str(&tx.amount).ok())
+            .sum()
+    }
+
+    // a bank's return of a deposit which has already settled, which can only be taken back by
+    // disputing it and charging it back
+    fn return_settled(&mut self, tx: &Transaction) -> Result<Outcome, PaymentError> {
+        self.run(&Transaction { tx_type: DISPUTE.to_string(), ..tx.clone() })?;
+        self.run(&Transaction { tx_type: CHARGEBACK.to_string(), ..tx.clone() })
+    }
+
+    // validate the passed transaction, check it against the rules and apply it
+    fn run(&mut self, tx: &Transaction) -> Result<Outcome, PaymentError> {
+        let valid = self.accounts.entry(tx.client).or_insert(Account::new(tx.client)).validate(tx, &self.global)?;
+
+        if valid.outcome == Outcome::AlreadyApplied {
+            return Ok(Outcome::AlreadyApplied);
+        }
+
+        let spread = match (tx.tx_type == EXCHANGE, valid.amount) {
+            (true, Some(amount)) => self.spread(tx, amount)?,
+            _ => None,
+        };
+
+        let key = self.global.key(tx);
+        let returned_payout = tx.tx_type == RETURNED && self.global.txs.get(&key).is_some_and(|stored| stored.tx_type == WITHDRAWAL);
+
         let account = self.accounts.entry(tx.client).or_insert(Account::new(tx.client));
 
-        account.process(&tx, &mut self.global)
+        // rules only apply to outflows
+        let outflow = if tx.tx_type == WITHDRAWAL || tx.tx_type == AUTHORIZE {
+            valid.amount
+        } else {
+            None
+        };
+
+        if let Some(amount) = outflow {
+            if let Some(rule) = self.global.history.check(&self.global.config.rules, tx.client, &tx.currency, amount, self.global.now) {
+                self.rule_hits.push(RuleHit { client: tx.client, tx: tx.tx, rule: rule.name.clone() });
+                return Err(PaymentError::LimitExceeded { rule: rule.name.clone() });
+            }
+        }
+
+        account.apply(tx, valid.amount, &mut self.global)?;
+
+        if let Some(amount) = outflow {
+            self.global.history.record(&self.global.config.rules, tx.client, &tx.currency, amount, self.global.now);
+        }
+
+        if tx.tx_type == DEPOSIT || tx.tx_type == WITHDRAWAL {
+            if let Some(amount) = valid.amount {
+                if let Some(alert) = self.global.monitor.observe(&self.global.config.alerts, tx, amount, self.global.now) {
+                    self.alerts.push(alert);
+                }
+            }
+        }
+
+        // imported debits were made by the bank, so only withdrawals from the input are paid out
+        if tx.tx_type == WITHDRAWAL && !tx.imported {
+            if let Some(amount) = valid.amount {
+                self.payouts.push(Payout {
+                    client: tx.client,
+                    tx: tx.tx,
+                    amount,
+                    currency: tx.currency.clone(),
+                    timestamp: tx.timestamp,
+                    source: tx.source.clone(),
+                });
+            }
+        }
+
+        // the funds of a returned withdrawal are back with the client, so it mustn't be paid again
+        if returned_payout {
+            let global = &self.global;
+            self.payouts.retain(|payout| global.scoped(payout.client, payout.tx, &payout.source) != key);
+        }
+
+        if tx.tx_type == DISPUTE || tx.tx_type == CHARGEBACK {
+            // the stats which changed are those in the currency of the disputed tx
+            let currency = self.global.txs.get(&key).map_or(tx.currency.as_str(), |disputed| disputed.currency.as_str());
+            let stats = account.risk(currency);
+
+            for rule in &self.global.config.risk {
+                // skip rules which wouldn't restrict the account any further
+                if account.status >= rule.status || !account.status.can_transition(rule.status) || !rule.applies(currency) {
+                    continue;
+                }
+
+                if let Some(detail) = rule.threshold.crossed(&stats) {
+                    account.restrict(rule.status, format!("risk rule {}: {}", rule.name, detail));
+                    account.track(tx, self.global.seq);
+
+                    self.risk_events.push(RiskEvent {
+                        client: tx.client,
+                        tx: tx.tx,
+                        rule: rule.name.clone(),
+                        currency: currency.to_string(),
+                        status: rule.status,
+                        detail,
+                    });
+                }
+            }
+        }
+
+        if let Some((house, amount)) = spread {
+            let balance = self.accounts.entry(house).or_insert(Account::new(house)).balance_mut(&tx.to_currency);
+            balance.available += amount;
+            balance.total += amount;
+        }
+
+        Ok(Outcome::Applied)
+    }
+
+    // the house account and the spread it is credited with for the passed exchange, checked before
+    // the client's account is touched so the spread can never go missing
+    fn spread(&self, tx: &Transaction, amount: Decimal) -> Result<Option<(ClientId, Decimal)>, PaymentError> {
+        let quote = self.global.config.rates.quote(&tx.currency, &tx.to_currency, amount, self.global.now)?;
+
+        if quote.spread == Decimal::ZERO {
+            return Ok(None);
+        }
+
+        let house = match self.global.config.house_account {
+            Some(house) => house,
+            None => return Err(PaymentError::HouseAccountMissing),
+        };
+
+        match self.accounts.get(&house) {
+            Some(account) if account.status == AccountStatus::Closed => Err(PaymentError::HouseAccountClosed),
+            _ => Ok(Some((house, quote.spread))),
+        }
+    }
+
+    // release the funds of all authorizations which have outlived the configured ttl
+    fn expire_authorizations(&mut self) {
+        while let Some(&(seq, _)) = self.global.expiries.front() {
+            if seq > self.global.seq {
+                break;
+            }
+
+            let id = match self.global.expiries.pop_front() {
+                Some((_, id)) => id,
+                None => break,
+            };
+
+            // already captured or voided
+            if let Some(auth) = self.global.authorizations.remove(&id) {
+                if let Some(account) = self.accounts.get_mut(&auth.client) {
+                    let balance = account.balance_mut(&auth.currency);
+                    balance.held -= auth.amount;
+                    balance.available += auth.amount;
+                }
+            }
+        }
+    }
+
+    // make the funds of all deposits which have waited out the settlement delay available
+    fn settle_deposits(&mut self) {
+        let clock = match self.global.config.settlement {
+            Settlement::Transactions(_) => self.global.seq,
+            Settlement::Seconds(_) => self.global.now,
+            _ => return,
+        };
+
+        while let Some(&(due, _)) = self.global.settlements.front() {
+            if due > clock {
+                break;
+            }
+
+            let id = match self.global.settlements.pop_front() {
+                Some((_, id)) => id,
+                None => break,
+            };
+
+            // already settled or returned
+            if let Some(deposit) = self.global.pending.remove(&id) {
+                if let Some(account) = self.accounts.get_mut(&deposit.client) {
+                    let balance = account.balance_mut(&deposit.currency);
+                    balance.pending -= deposit.amount;
+                    balance.available += deposit.amount;
+                }
+            }
+        }
     }
 }
 
 impl Account {
     // ctor
-    pub fn new(id: u16) -> Account {
+    pub fn new(id: ClientId) -> Account {
         Account {
             client: id,
-            available: dec!(0.0),
-            held: dec!(0.0),
-            total: dec!(0.0),
-            locked: false,
+            balances: BTreeMap::new(),
+            status: AccountStatus::Active,
+            reason: String::new(),
+            admin: false,
+            lock_events: Vec::new(),
+            risk_stats: BTreeMap::new(),
+            withdrawal_disputes: 0,
+            dispute_freeze: false,
+        }
+    }
+
+    // the balance in the passed currency, zero if the account doesn't hold it
+    pub fn balance(&self, currency: &str) -> Balance {
+        self.balances.get(currency).copied().unwrap_or_default()
+    }
+
+    // the balance in the passed currency, added if the account doesn't hold it yet
+    pub fn balance_mut(&mut self, currency: &str) -> &mut Balance {
+        self.balances.entry(currency.to_string()).or_default()
+    }
+
+    // the dispute activity in the passed currency, none if there hasn't been any
+    pub fn risk(&self, currency: &str) -> RiskStats {
+        self.risk_stats.get(currency).cloned().unwrap_or_default()
+    }
+
+    // the dispute activity in the passed currency, added if there hasn't been any yet
+    pub fn risk_mut(&mut self, currency: &str) -> &mut RiskStats {
+        self.risk_stats.entry(currency.to_string()).or_default()
+    }
+
+    // move the account to the passed status, if that transition is allowed
+    pub fn set_status(&mut self, status: AccountStatus, reason: String) -> Result<(), PaymentError> {
+        if !self.status.can_transition(status) {
+            return Err(PaymentError::InvalidStatusTransition);
+        }
+
+        self.status = status;
+        self.dispute_freeze = false;
+
+        if status == AccountStatus::Active {
+            self.reason = String::new();
+            self.lock_events.clear();
+        } else {
+            self.reason = reason;
+        }
+
+        Ok(())
+    }
+
+    // the event which moved the account away from active, if it isn't active
+    pub fn locked_by(&self) -> Option<&LockEvent> {
+        self.lock_events.first()
+    }
+
+    // remember the passed tx if it left the account restricted
+    pub(crate) fn track(&mut self, tx: &Transaction, ordinal: u64) {
+        if self.status != AccountStatus::Active {
+            self.lock_events.push(LockEvent {
+                tx: tx.tx,
+                kind: tx.tx_type.clone(),
+                ordinal,
+            });
+        }
+    }
+
+    // move the account to the passed status unless it is already at least as restricted
+    pub(crate) fn restrict(&mut self, status: AccountStatus, reason: String) {
+        if self.status < status {
+            self.status = status;
+            self.reason = reason;
+            self.dispute_freeze = false;
         }
     }
 
     // process the passed transaction for this account
-    pub fn process(&mut self, tx: &Transaction, global: &mut GlobalData) -> Result<(), PaymentError> {
-        if tx.tx_type == WITHDRAWAL || tx.tx_type == DEPOSIT {
-            if self.locked {
-                return Err(PaymentError::AccountLocked);
-            }
+    pub fn process(&mut self, tx: &Transaction, global: &mut GlobalData) -> Result<Outcome, PaymentError> {
+        let result = self.validate(tx, global).and_then(|valid| match valid.outcome {
+            Outcome::Applied => self.apply(tx, valid.amount, global),
+            Outcome::AlreadyApplied => Ok(Outcome::AlreadyApplied),
+        });
 
-            let amount = match Decimal::from_str(&tx.amount) {
-                Ok(amt) => amt,
-                Err(_) => return Err(PaymentError::BadDecimal)
-            };
+        if result.is_err() {
+            global.burn(tx);
+        }
 
-            if tx.tx_type == WITHDRAWAL && self.available < amount {
-                return Err(PaymentError::InsufficientFunds);
-            }
+        result
+    }
 
-            if global.txs.contains_key(&tx.tx) {
-                return Err(PaymentError::DuplicateTransaction);
-            }
+    // run the passed transaction through the validation pipeline, see validate.rs
+    pub fn validate(&self, tx: &Transaction, global: &GlobalData) -> Result<Validated, PaymentError> {
+        let amount = check_schema(tx)?;
+        let key = global.key(tx);
+        let outcome = check_duplicate(tx, global.txs.get(&key), global.burned.contains(&key))?;
+
+        // exact replays are acknowledged whatever state the account is in now
+        if outcome == Outcome::Applied {
+            check_status(self, tx)?;
+            check_funds(self, tx, amount)?;
+        }
+
+        Ok(Validated { outcome, amount })
+    }
 
-            global.txs.insert(tx.tx, tx.clone());
+    // apply a validated transaction to this account, with its parsed amount if it carries one
+    #[allow(clippy::needless_return)]
+    pub(crate) fn apply(&mut self, tx: &Transaction, amount: Option<Decimal>, global: &mut GlobalData) -> Result<Outcome, PaymentError> {
+        let key = global.key(tx);
+
+        if tx.tx_type == WITHDRAWAL || tx.tx_type == DEPOSIT {
+            let amount = amount.unwrap_or_default();
+
+            global.txs.insert(key.clone(), tx.clone());
+
+            let balance = self.balances.entry(tx.currency.clone()).or_default();
 
             if tx.tx_type == WITHDRAWAL {
-                self.available -= amount;
-                self.total -= amount;
-            } else { // DEPOSIT
-                self.available += amount;
-                self.total += amount;
+                balance.available -= amount;
+                balance.total -= amount;
+            } else if global.config.settlement == Settlement::Immediate { // DEPOSIT
+                self.risk_stats.entry(tx.currency.clone()).or_default().deposited += amount;
+                balance.available += amount;
+                balance.total += amount;
+            } else {
+                let due = match global.config.settlement {
+                    Settlement::Transactions(delay) => Some(global.seq + delay),
+                    Settlement::Seconds(delay) => Some(global.now + delay),
+                    _ => None,
+                };
+
+                if let Some(due) = due {
+                    global.settlements.push_back((due, key.clone()));
+                }
+
+                global.pending.insert(key.clone(), PendingDeposit { client: tx.client, currency: tx.currency.clone(), amount });
+                balance.pending += amount;
+                self.risk_stats.entry(tx.currency.clone()).or_default().deposited += amount;
+                balance.total += amount;
             }
             
-            return Ok(());
+            return Ok(Outcome::Applied);
 
         } else if tx.tx_type == DISPUTE {
-            if global.disputes.contains(&tx.tx) {
+            if global.disputes.contains(&key) {
                 return Err(PaymentError::AlreadyDisputed);
             }
 
-            let disputed_tx = match global.txs.get(&tx.tx) {
+            let disputed_tx = match global.txs.get(&key) {
                 Some(dtx) => dtx,
                 None => return Err(PaymentError::DisputedTxNotFound)
             };
@@ -150,29 +864,56 @@ impl Account {
             if disputed_tx.client != tx.client {
                 return Err(PaymentError::DisputedWrongClient);
             }
-            
-            global.disputes.insert(tx.tx);
+
+            // only client initiated movements of funds can be disputed
+            if disputed_tx.tx_type != DEPOSIT && disputed_tx.tx_type != WITHDRAWAL {
+                return Err(PaymentError::NotDisputable);
+            }
+
+            // unsettled deposits are reversed with a returned tx instead
+            if global.pending.contains_key(&key) {
+                return Err(PaymentError::DepositNotSettled);
+            }
+
+            // a returned deposit has already been taken back out of the account
+            if global.returned.contains(&key) {
+                return Err(PaymentError::NotDisputable);
+            }
+
+            global.disputes.insert(key.clone());
             
             let amount = match Decimal::from_str(&disputed_tx.amount) {
                 Ok(amt) => amt,
                 Err(_) => return Err(PaymentError::BadDecimal)
             };
  
+            // funds are held in the currency of the disputed tx
             if disputed_tx.tx_type == DEPOSIT {
-                self.available -= amount;
-                self.held += amount;
+                let balance = self.balances.entry(disputed_tx.currency.clone()).or_default();
+                balance.available -= amount;
+                balance.held += amount;
+
+                let risk = self.risk_stats.entry(disputed_tx.currency.clone()).or_default();
+                risk.disputed += amount;
+                risk.open_disputes += 1;
             } else { // WITHDRAWAL
-                self.locked = true;
+                if self.status < AccountStatus::Frozen {
+                    self.restrict(AccountStatus::Frozen, format!("dispute of tx {}", tx.tx));
+                    self.dispute_freeze = true;
+                }
+
+                self.withdrawal_disputes += 1;
+                self.track(tx, global.seq);
             }
             
-            return Ok(());
+            return Ok(Outcome::Applied);
 
         } else if tx.tx_type == RESOLVE || tx.tx_type == CHARGEBACK {
-            if !global.disputes.contains(&tx.tx) {
+            if !global.disputes.contains(&key) {
                 return Err(PaymentError::NotDisputed);
             }
 
-            let disputed_tx = match global.txs.get(&tx.tx) {
+            let disputed_tx = match global.txs.get(&key) {
                 Some(dtx) => dtx,
                 None => return Err(PaymentError::DisputedTxNotFound)
             };
@@ -181,32 +922,182 @@ impl Account {
                 return Err(PaymentError::DisputedWrongClient);
             }
             
-            global.disputes.remove(&tx.tx);
+            global.disputes.remove(&key);
 
             let amount = match Decimal::from_str(&disputed_tx.amount) {
                 Ok(amt) => amt,
                 Err(_) => return Err(PaymentError::BadDecimal)
             };
 
+            if disputed_tx.tx_type == DEPOSIT {
+                self.risk_stats.entry(disputed_tx.currency.clone()).or_default().open_disputes -= 1;
+            } else { // WITHDRAWAL
+                self.withdrawal_disputes -= 1;
+            }
+
+            let balance = self.balances.entry(disputed_tx.currency.clone()).or_default();
+
             if tx.tx_type == RESOLVE {
                 if disputed_tx.tx_type == DEPOSIT {
-                    self.available += amount;
-                    self.held -= amount;
-                } else { // WITHDRAWAL
-                    self.locked = false;
+                    balance.available += amount;
+                    balance.held -= amount;
+                } else if self.dispute_freeze && self.withdrawal_disputes == 0 { // WITHDRAWAL
+                    // a freeze from an admin or a risk rule, or a lock from a chargeback, outlives
+                    // the disputes, as does one from another dispute which is still open
+                    self.set_status(AccountStatus::Active, String::new())?;
                 }
             } else { // CHARGEBACK
                 if disputed_tx.tx_type == DEPOSIT {
-                    self.held -= amount;
-                    self.total -= amount;
-                    self.locked = true;
-                } else { // WITHDRAWAL
-                    // should already be locked
-                    self.locked = true;
+                    balance.held -= amount;
+                    balance.total -= amount;
+                    self.risk_stats.entry(disputed_tx.currency.clone()).or_default().charged_back += amount;
                 }
+
+                self.restrict(AccountStatus::Locked, format!("chargeback of tx {}", tx.tx));
+                self.track(tx, global.seq);
             }
             
-            return Ok(());
+            return Ok(Outcome::Applied);
+
+        } else if tx.tx_type == AUTHORIZE {
+            let amount = amount.unwrap_or_default();
+
+            global.txs.insert(key.clone(), tx.clone());
+            global.authorizations.insert(key.clone(), Authorization { client: tx.client, currency: tx.currency.clone(), amount });
+
+            if let Some(ttl) = global.config.authorization_ttl {
+                global.expiries.push_back((global.seq + ttl, key.clone()));
+            }
+
+            let balance = self.balance_mut(&tx.currency);
+            balance.available -= amount;
+            balance.held += amount;
+
+            Ok(Outcome::Applied)
+
+        } else if tx.tx_type == CAPTURE || tx.tx_type == VOID {
+            let auth = match global.authorizations.get(&key) {
+                Some(auth) => auth,
+                None => return Err(PaymentError::AuthorizationNotFound)
+            };
+
+            if auth.client != tx.client {
+                return Err(PaymentError::AuthorizationWrongClient);
+            }
+
+            // no amount captures the full authorization, a partial capture releases the rest
+            let captured = if tx.tx_type == VOID {
+                dec!(0.0)
+            } else {
+                amount.unwrap_or(auth.amount)
+            };
+
+            if captured > auth.amount {
+                return Err(PaymentError::CaptureExceedsAuthorization);
+            }
+
+            // captured in the currency of the authorization
+            let balance = self.balances.entry(auth.currency.clone()).or_default();
+            balance.held -= auth.amount;
+            balance.available += auth.amount - captured;
+            balance.total -= captured;
+
+            global.authorizations.remove(&key);
+
+            Ok(Outcome::Applied)
+
+        } else if let Some(withdrawal) = global.txs.get(&key).filter(|stored| tx.tx_type == RETURNED && stored.tx_type == WITHDRAWAL) {
+            // a payout the bank couldn't make, whose funds go back to the client, which only the
+            // bank's return file can say, as a client's own input could otherwise credit itself
+            if !tx.imported {
+                return Err(PaymentError::ReturnNotImported);
+            }
+
+            if global.returned.contains(&key) {
+                return Err(PaymentError::AlreadyReturned);
+            }
+
+            if withdrawal.client != tx.client {
+                return Err(PaymentError::ReturnedWrongClient);
+            }
+
+            let amount = match Decimal::from_str(&withdrawal.amount) {
+                Ok(amt) => amt,
+                Err(_) => return Err(PaymentError::BadDecimal)
+            };
+
+            let balance = self.balances.entry(withdrawal.currency.clone()).or_default();
+            balance.available += amount;
+            balance.total += amount;
+
+            global.returned.insert(key.clone());
+
+            Ok(Outcome::Applied)
+
+        } else if tx.tx_type == SETTLE || tx.tx_type == RETURNED {
+            let deposit = match global.pending.get(&key) {
+                Some(deposit) => deposit,
+                None => return Err(PaymentError::DepositNotPending)
+            };
+
+            if deposit.client != tx.client {
+                return Err(PaymentError::PendingWrongClient);
+            }
+
+            let balance = self.balances.entry(deposit.currency.clone()).or_default();
+            balance.pending -= deposit.amount;
+
+            if tx.tx_type == SETTLE {
+                balance.available += deposit.amount;
+            } else { // RETURNED
+                balance.total -= deposit.amount;
+                self.risk_stats.entry(deposit.currency.clone()).or_default().deposited -= deposit.amount;
+                global.returned.insert(key.clone());
+            }
+
+            global.pending.remove(&key);
+
+            Ok(Outcome::Applied)
+
+        } else if tx.tx_type == EXCHANGE {
+            let amount = amount.unwrap_or_default();
+            let quote = global.config.rates.quote(&tx.currency, &tx.to_currency, amount, global.now)?;
+
+            global.txs.insert(key.clone(), tx.clone());
+
+            let from = self.balance_mut(&tx.currency);
+            from.available -= amount;
+            from.total -= amount;
+
+            let to = self.balance_mut(&tx.to_currency);
+            to.available += quote.converted;
+            to.total += quote.converted;
+
+            Ok(Outcome::Applied)
+
+        } else if is_admin(&tx.tx_type) {
+            if tx.tx_type == ADJUST {
+                let amount = amount.unwrap_or_default();
+
+                let balance = self.balance_mut(&tx.currency);
+                balance.available += amount;
+                balance.total += amount;
+            } else if tx.tx_type == FREEZE {
+                self.set_status(AccountStatus::Frozen, admin_reason(tx))?;
+            } else if tx.tx_type == CLOSE {
+                self.set_status(AccountStatus::Closed, admin_reason(tx))?;
+            } else { // UNLOCK
+                self.set_status(AccountStatus::Active, String::new())?;
+            }
+
+            if tx.tx_type != ADJUST {
+                self.track(tx, global.seq);
+            }
+
+            global.txs.insert(key.clone(), tx.clone());
+            self.admin = true;
+
+            Ok(Outcome::Applied)
 
         } else {
             return Err(PaymentError::UnknownTxType);
@@ -215,8 +1106,28 @@ impl Account {
 
     // rescale all the decimal vars for uniform output
     pub fn rescale(&mut self, scale: u32) {
-        self.available.rescale(scale);
-        self.held.rescale(scale);
-        self.total.rescale(scale);
+        for balance in self.balances.values_mut() {
+            balance.rescale(scale);
+        }
+    }
+
+    // the balances rounded to the passed output precision, by currency
+    pub fn round(&self, precision: &Precision) -> Result<BTreeMap<String, Balance>, PaymentError> {
+        let mut balances = BTreeMap::new();
+
+        for (currency, balance) in &self.balances {
+            balances.insert(currency.clone(), balance.round(precision)?);
+        }
+
+        Ok(balances)
+    }
+}
+
+// the status reason recorded for an admin tx, falling back to its type
+fn admin_reason(tx: &Transaction) -> String {
+    if tx.reason.is_empty() {
+        tx.tx_type.clone()
+    } else {
+        tx.reason.clone()
     }
 }
@@ -0,0 +1,386 @@
+use quick_xml::events::{BytesDecl, BytesText, Event};
+use quick_xml::Writer;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::io::{self, Read, Write};
+
+use crate::fx::minor_units;
+use crate::ids::{ClientId, TxId};
+use crate::import::format_date;
+use crate::nacha::{PayoutTrace, TraceMap, RECORD_LEN};
+
+// NACHA files are written in blocks of this many records, padded with records of 9s
+const BLOCKING_FACTOR: usize = 10;
+
+// the only currency NACHA files can pay out
+const USD: &str = "USD";
+
+// an accepted withdrawal, which has to be paid out to the client's bank account
+#[derive(Clone, Debug, PartialEq)]
+pub struct Payout {
+    pub client: ClientId,
+    pub tx: TxId,
+    pub amount: Decimal,
+    pub currency: String,
+    pub timestamp: Option<u64>,
+    // the source of the withdrawal, for per source ids
+    pub source: Option<String>,
+}
+
+// the bank account a client is paid out to
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Payee {
+    pub client: ClientId,
+    pub name: String,
+    // a DFI account number for NACHA, or an IBAN for pain.001
+    pub account: String,
+    // a routing number for NACHA, or a BIC for pain.001, which may be empty
+    #[serde(default)]
+    pub bank: String,
+}
+
+// the bank accounts of the clients who can be paid out
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PayeeDirectory {
+    payees: HashMap<ClientId, Payee>,
+}
+
+impl PayeeDirectory {
+    // ctor
+    pub fn new() -> PayeeDirectory {
+        Default::default()
+    }
+
+    // load a payee directory from CSV with client,name,account,bank columns
+    pub fn load<R: Read>(reader: R) -> Result<PayeeDirectory, Box<dyn Error>> {
+        let mut csv_reader = csv::Reader::from_reader(reader);
+        let mut payees = PayeeDirectory::new();
+
+        for result in csv_reader.deserialize() {
+            payees.insert(result?);
+        }
+
+        Ok(payees)
+    }
+
+    // add the passed payee, replacing any earlier one of the same client
+    pub fn insert(&mut self, payee: Payee) {
+        self.payees.insert(payee.client, payee);
+    }
+
+    // the payee withdrawals of the passed client are paid to
+    pub fn get(&self, client: ClientId) -> Option<&Payee> {
+        self.payees.get(&client)
+    }
+}
+
+// the business paying out, and the account the payouts are debited from
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Originator {
+    pub name: String,
+    // the NACHA company id, or the pain.001 initiating party id
+    #[serde(default)]
+    pub id: String,
+    // the account the payouts are debited from, unused by NACHA
+    #[serde(default)]
+    pub account: String,
+    // the ODFI's routing number for NACHA, or a BIC for pain.001
+    #[serde(default)]
+    pub bank: String,
+    // the currency of payouts whose withdrawal didn't name one
+    #[serde(default)]
+    pub currency: String,
+}
+
+impl Originator {
+    // load the originator from JSON
+    pub fn load<R: Read>(reader: R) -> Result<Originator, Box<dyn Error>> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+}
+
+// true if the passed 9 digit routing number has a valid check digit
+pub fn valid_routing(routing: &str) -> bool {
+    let digits: Vec<u32> = routing.chars().filter_map(|c| c.to_digit(10)).collect();
+
+    if routing.len() != 9 || digits.len() != 9 {
+        return false;
+    }
+
+    let sum = 3 * (digits[0] + digits[3] + digits[6]) + 7 * (digits[1] + digits[4] + digits[7]) + digits[2] + digits[5] + digits[8];
+
+    sum.is_multiple_of(10)
+}
+
+// the passed amount in minor units, if it has no more decimal places than the currency
+fn minor_amount(payout: &Payout, currency: &str) -> Result<i64, Box<dyn Error>> {
+    let scaled = payout.amount * Decimal::from(10i64.pow(minor_units(currency)));
+
+    match scaled.to_i64() {
+        Some(minor) if scaled.fract().is_zero() && minor > 0 => Ok(minor),
+        _ => Err(format!("Payout of tx {} can't be paid as {} {}", payout.tx, payout.amount, currency).into()),
+    }
+}
+
+// a NACHA field, upper case, left justified and truncated or padded with spaces
+fn alpha(value: &str, len: usize) -> String {
+    // records are fixed width ASCII
+    let value: String = value.chars()
+        .map(|c| if c.is_ascii_graphic() { c.to_ascii_uppercase() } else { ' ' })
+        .collect();
+
+    format!("{:<len$.len$}", value, len = len)
+}
+
+// a NACHA field, right justified and padded with zeros, which fails if it doesn't fit
+fn numeric(value: i64, len: usize) -> Result<String, Box<dyn Error>> {
+    let field = format!("{:0len$}", value, len = len);
+
+    if field.len() > len {
+        return Err(format!("{} doesn't fit in a {} digit NACHA field", value, len).into());
+    }
+
+    Ok(field)
+}
+
+// write the passed payouts as a NACHA file with a single PPD batch of credits, effective on the
+// passed date
+//
+// each entry's individual id is the client, cut to 15 characters for uuid ids, and its trace number
+// is the originator's routing number then the next sequence number in traces, as a tx id may not
+// fit in 7 digits.  the trace numbers are added to traces once the file is written, so returns of
+// the payouts can be matched to their withdrawals
+//
+// the file's credit total has to match the passed total of the accepted withdrawals, e.g.
+// Ledger::payout_total, so a payout which was lost or changed after its withdrawal was accepted
+// isn't paid
+pub fn write_nacha_payouts<W: Write>(mut writer: W, payouts: &[Payout], total: Decimal, payees: &PayeeDirectory, originator: &Originator, date: u64, traces: &mut TraceMap) -> Result<(), Box<dyn Error>> {
+    if !valid_routing(&originator.bank) {
+        return Err(format!("Originator routing number {} is invalid", originator.bank).into());
+    }
+
+    let day = format_date(date);
+    let yymmdd = format!("{}{}{}", &day[2..4], &day[5..7], &day[8..10]);
+    let hhmm = format!("{:02}{:02}", date % 86400 / 3600, date % 3600 / 60);
+    let odfi = &originator.bank[..8];
+
+    let mut records = vec![
+        format!("101 {}{}{}{}A094101{}{}{}", originator.bank, alpha(&originator.id, 10), yymmdd, hhmm, alpha("", 23), alpha(&originator.name, 23), alpha("", 8)),
+        format!("5220{}{}{}PPD{}{}{}   1{}0000001", alpha(&originator.name, 16), alpha("", 20), alpha(&originator.id, 10), alpha("PAYOUT", 10), yymmdd, yymmdd, odfi),
+    ];
+
+    let mut hash: i64 = 0;
+    let mut credits: i64 = 0;
+    let mut written = Vec::new();
+    let first = traces.next_sequence(odfi) as i64;
+
+    for (i, payout) in payouts.iter().enumerate() {
+        let currency = if payout.currency.is_empty() { &originator.currency } else { &payout.currency };
+
+        if !currency.is_empty() && currency != USD {
+            return Err(format!("Payout of tx {} is in {}, NACHA files can only pay {}", payout.tx, currency, USD).into());
+        }
+
+        let payee = payees.get(payout.client).ok_or_else(|| format!("No payee for client {}", payout.client))?;
+
+        if !valid_routing(&payee.bank) {
+            return Err(format!("Routing number {} of client {} is invalid", payee.bank, payout.client).into());
+        }
+
+        let trace = format!("{}{}", odfi, numeric(first + i as i64, 7)?);
+        let cents = minor_amount(payout, USD)?;
+
+        hash += payee.bank[..8].parse::<i64>()?;
+        credits += cents;
+
+        // 22 is a credit to a checking account
+        records.push(format!("622{}{}{}{}{}  0{}", payee.bank, alpha(&payee.account, 17), numeric(cents, 10)?, alpha(&payout.client.to_string(), 15), alpha(&payee.name, 22), trace));
+        written.push(PayoutTrace { trace, client: payout.client, tx: payout.tx, source: payout.source.clone() });
+    }
+
+    if Decimal::new(credits, 2) != total {
+        return Err(format!("NACHA credit total {} doesn't match the withdrawals' total {}", Decimal::new(credits, 2), total).into());
+    }
+
+    // the entry hash is the sum of the RDFI routing numbers, truncated to its last 10 digits
+    let hash = numeric(hash % 10_000_000_000, 10)?;
+    let entries = payouts.len() as i64;
+
+    records.push(format!("8220{}{}{}{}{}{}{}{}0000001", numeric(entries, 6)?, hash, numeric(0, 12)?, numeric(credits, 12)?, alpha(&originator.id, 10), alpha("", 19), alpha("", 6), odfi));
+
+    let blocks = (records.len() + 1).div_ceil(BLOCKING_FACTOR) as i64;
+    records.push(format!("9{}{}{}{}{}{}{}", numeric(1, 6)?, numeric(blocks, 6)?, numeric(entries, 8)?, hash, numeric(0, 12)?, numeric(credits, 12)?, alpha("", 39)));
+
+    while !records.len().is_multiple_of(BLOCKING_FACTOR) {
+        records.push("9".repeat(RECORD_LEN));
+    }
+
+    for record in &records {
+        writeln!(writer, "{}", record)?;
+    }
+
+    writer.flush()?;
+
+    for trace in written {
+        traces.insert(trace);
+    }
+
+    Ok(())
+}
+
+// write the passed payouts as an ISO 20022 pain.001 credit transfer initiation, with a payment
+// information block per currency, to be executed on the passed date
+//
+// the control sum has to match the passed total of the accepted withdrawals, as for NACHA
+pub fn write_pain001_payouts<W: Write>(writer: W, payouts: &[Payout], expected: Decimal, payees: &PayeeDirectory, originator: &Originator, date: u64) -> Result<(), Box<dyn Error>> {
+    // (payout, payee, amount) of each currency, checked before anything is written
+    let mut currencies: BTreeMap<&str, Vec<(&Payout, &Payee, Decimal)>> = BTreeMap::new();
+
+    for payout in payouts {
+        let currency = if payout.currency.is_empty() { &originator.currency } else { &payout.currency };
+
+        if currency.is_empty() {
+            return Err(format!("Payout of tx {} has no currency", payout.tx).into());
+        }
+
+        let payee = payees.get(payout.client).ok_or_else(|| format!("No payee for client {}", payout.client))?;
+        let amount = Decimal::new(minor_amount(payout, currency)?, minor_units(currency));
+
+        currencies.entry(currency.as_str()).or_default().push((payout, payee, amount));
+    }
+
+    let total: Decimal = currencies.values().flatten().map(|(_, _, amount)| *amount).sum();
+
+    if total != expected {
+        return Err(format!("pain.001 control sum {} doesn't match the withdrawals' total {}", total, expected).into());
+    }
+
+    let day = format_date(date);
+    let message_id = format!("PAYOUT-{}", date);
+    let created = format!("{}T{:02}:{:02}:{:02}", day, date % 86400 / 3600, date % 3600 / 60, date % 60);
+
+    let mut xml = Writer::new_with_indent(writer, b' ', 2);
+    xml.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+
+    xml.create_element("Document")
+        .with_attribute(("xmlns", "urn:iso:std:iso:20022:tech:xsd:pain.001.001.03"))
+        .write_inner_content(|xml| {
+            xml.create_element("CstmrCdtTrfInitn").write_inner_content(|xml| {
+                xml.create_element("GrpHdr").write_inner_content(|xml| {
+                    text(xml, "MsgId", &message_id)?;
+                    text(xml, "CreDtTm", &created)?;
+                    text(xml, "NbOfTxs", &payouts.len().to_string())?;
+                    text(xml, "CtrlSum", &total.to_string())?;
+                    party(xml, "InitgPty", &originator.name, &originator.id)
+                })?;
+
+                for (currency, transfers) in &currencies {
+                    let sum: Decimal = transfers.iter().map(|(_, _, amount)| *amount).sum();
+
+                    xml.create_element("PmtInf").write_inner_content(|xml| {
+                        text(xml, "PmtInfId", &format!("{}-{}", message_id, currency))?;
+                        text(xml, "PmtMtd", "TRF")?;
+                        text(xml, "NbOfTxs", &transfers.len().to_string())?;
+                        text(xml, "CtrlSum", &sum.to_string())?;
+                        text(xml, "ReqdExctnDt", &day)?;
+                        party(xml, "Dbtr", &originator.name, "")?;
+                        account(xml, "DbtrAcct", &originator.account)?;
+                        agent(xml, "DbtrAgt", &originator.bank)?;
+
+                        for (payout, payee, amount) in transfers {
+                            xml.create_element("CdtTrfTxInf").write_inner_content(|xml| {
+                                xml.create_element("PmtId").write_inner_content(|xml| text(xml, "EndToEndId", &payout.tx.to_string()))?;
+                                xml.create_element("Amt").write_inner_content(|xml| {
+                                    xml.create_element("InstdAmt")
+                                        .with_attribute(("Ccy", *currency))
+                                        .write_text_content(BytesText::new(&amount.to_string()))?;
+                                    Ok(())
+                                })?;
+
+                                if !payee.bank.is_empty() {
+                                    agent(xml, "CdtrAgt", &payee.bank)?;
+                                }
+
+                                party(xml, "Cdtr", &payee.name, "")?;
+                                account(xml, "CdtrAcct", &payee.account)?;
+                                xml.create_element("RmtInf").write_inner_content(|xml| text(xml, "Ustrd", &format!("Withdrawal {}", payout.tx)))?;
+                                Ok(())
+                            })?;
+                        }
+
+                        Ok(())
+                    })?;
+                }
+
+                Ok(())
+            })?;
+
+            Ok(())
+        })?;
+
+    writeln!(xml.get_mut())?;
+    xml.get_mut().flush()?;
+
+    Ok(())
+}
+
+// write an element containing only text
+fn text<W: Write>(xml: &mut Writer<W>, name: &str, value: &str) -> io::Result<()> {
+    xml.create_element(name).write_text_content(BytesText::new(value))?;
+
+    Ok(())
+}
+
+// write a party with a name and an optional id
+fn party<W: Write>(xml: &mut Writer<W>, name: &str, party_name: &str, id: &str) -> io::Result<()> {
+    xml.create_element(name).write_inner_content(|xml| {
+        text(xml, "Nm", party_name)?;
+
+        if !id.is_empty() {
+            xml.create_element("Id").write_inner_content(|xml| {
+                xml.create_element("OrgId").write_inner_content(|xml| {
+                    xml.create_element("Othr").write_inner_content(|xml| text(xml, "Id", id))?;
+                    Ok(())
+                })?;
+                Ok(())
+            })?;
+        }
+
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+// write an account, as an IBAN if it looks like one
+fn account<W: Write>(xml: &mut Writer<W>, name: &str, account: &str) -> io::Result<()> {
+    let bytes = account.as_bytes();
+    let iban = bytes.len() > 4 && bytes[..2].iter().all(u8::is_ascii_uppercase) && bytes[2..4].iter().all(u8::is_ascii_digit);
+
+    xml.create_element(name).write_inner_content(|xml| {
+        xml.create_element("Id").write_inner_content(|xml| {
+            if iban {
+                text(xml, "IBAN", account)
+            } else {
+                xml.create_element("Othr").write_inner_content(|xml| text(xml, "Id", account))?;
+                Ok(())
+            }
+        })?;
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+// write a financial institution by its BIC
+fn agent<W: Write>(xml: &mut Writer<W>, name: &str, bic: &str) -> io::Result<()> {
+    xml.create_element(name).write_inner_content(|xml| {
+        xml.create_element("FinInstnId").write_inner_content(|xml| text(xml, "BIC", bic))?;
+        Ok(())
+    })?;
+
+    Ok(())
+}
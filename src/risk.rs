@@ -0,0 +1,84 @@
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::Deserialize;
+use std::error::Error;
+use std::io::Read;
+
+use crate::ids::{ClientId, TxId};
+use crate::payments::AccountStatus;
+
+// running totals of an account's dispute activity in one currency, so amounts are only ever
+// compared with amounts in the same currency
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RiskStats {
+    pub deposited: Decimal,
+    pub disputed: Decimal,
+    pub charged_back: Decimal,
+    pub open_disputes: u32,
+}
+
+// a level of dispute activity which is considered suspicious
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Threshold {
+    // more deposits disputed at once
+    OpenDisputes(u32),
+    // a larger share of deposited funds charged back
+    ChargebackRatio(Decimal),
+    // a larger amount of deposits disputed in total
+    DisputedAmount(Decimal),
+}
+
+impl Threshold {
+    // a description of how the passed stats cross this threshold, if they do
+    pub fn crossed(&self, stats: &RiskStats) -> Option<String> {
+        match *self {
+            Threshold::OpenDisputes(max) if stats.open_disputes > max => {
+                Some(format!("{} open disputes exceed {}", stats.open_disputes, max))
+            },
+            Threshold::ChargebackRatio(max) if stats.deposited > dec!(0) && stats.charged_back / stats.deposited > max => {
+                Some(format!("{} of {} deposited charged back, more than a ratio of {}", stats.charged_back, stats.deposited, max))
+            },
+            Threshold::DisputedAmount(max) if stats.disputed > max => {
+                Some(format!("{} disputed exceeds {}", stats.disputed, max))
+            },
+            _ => None,
+        }
+    }
+}
+
+// a threshold and the status an account is moved to once it crosses it
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct RiskRule {
+    pub name: String,
+    pub threshold: Threshold,
+    pub status: AccountStatus,
+    // the only currency whose stats the rule checks, or every currency if None, each one on its own
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+impl RiskRule {
+    // true if the rule checks the stats of the passed currency
+    pub(crate) fn applies(&self, currency: &str) -> bool {
+        self.currency.as_deref().is_none_or(|only| only == currency)
+    }
+
+    // load a list of risk rules from JSON
+    pub fn load<R: Read>(reader: R) -> Result<Vec<RiskRule>, Box<dyn Error>> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+}
+
+// an account which was restricted by a risk rule
+#[derive(Clone, Debug, PartialEq)]
+pub struct RiskEvent {
+    pub client: ClientId,
+    // the tx which pushed the account over the threshold
+    pub tx: TxId,
+    pub rule: String,
+    // the currency whose stats crossed the threshold
+    pub currency: String,
+    pub status: AccountStatus,
+    pub detail: String,
+}
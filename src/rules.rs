@@ -0,0 +1,130 @@
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::io::Read;
+
+use crate::ids::{ClientId, TxId};
+
+const SECONDS_PER_DAY: u64 = 86400;
+
+// the period over which a velocity limit is evaluated
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Window {
+    // the utc calendar day of the tx
+    Daily,
+    // the passed number of seconds up to the tx
+    Rolling(u64),
+}
+
+impl Window {
+    // true if a tx at the passed time falls into the window ending now
+    fn contains(self, time: u64, now: u64) -> bool {
+        match self {
+            Window::Daily => time >= now - now % SECONDS_PER_DAY,
+            Window::Rolling(seconds) => time + seconds > now,
+        }
+    }
+
+    // the longest time a tx can stay inside the window
+    fn length(self) -> u64 {
+        match self {
+            Window::Daily => SECONDS_PER_DAY,
+            Window::Rolling(seconds) => seconds,
+        }
+    }
+}
+
+// what a rule restricts
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Limit {
+    // largest amount of a single outflow
+    MaxAmount(Decimal),
+    // largest sum of outflows within the window
+    MaxTotal { window: Window, amount: Decimal },
+    // largest number of outflows within the window
+    MaxCount { window: Window, count: usize },
+}
+
+// a named limit on the withdrawals and authorizations of every client
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Rule {
+    pub name: String,
+    pub limit: Limit,
+    // the only currency the rule limits, or every currency if None, each one on its own
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+impl Rule {
+    // true if the rule limits outflows in the passed currency
+    fn applies(&self, currency: &str) -> bool {
+        self.currency.as_deref().is_none_or(|only| only == currency)
+    }
+
+    // load a list of rules from JSON
+    pub fn load<R: Read>(reader: R) -> Result<Vec<Rule>, Box<dyn Error>> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+}
+
+// a rule which rejected a tx
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuleHit {
+    pub client: ClientId,
+    pub tx: TxId,
+    pub rule: String,
+}
+
+// (time, amount) of the recent outflows of each client, by currency, as amounts in different
+// currencies can't be added up
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    outflows: HashMap<(ClientId, String), VecDeque<(u64, Decimal)>>,
+}
+
+impl History {
+    // find the first rule which the passed outflow would break, counting only the client's earlier
+    // outflows in the same currency
+    pub fn check<'a>(&self, rules: &'a [Rule], client: ClientId, currency: &str, amount: Decimal, now: u64) -> Option<&'a Rule> {
+        let empty = VecDeque::new();
+        let outflows = self.outflows.get(&(client, currency.to_string())).unwrap_or(&empty);
+
+        rules.iter().filter(|rule| rule.applies(currency)).find(|rule| match rule.limit {
+            Limit::MaxAmount(max) => amount > max,
+            Limit::MaxTotal { window, amount: max } => {
+                let total: Decimal = outflows.iter().filter(|(time, _)| window.contains(*time, now)).map(|(_, amt)| *amt).sum();
+                total + amount > max
+            },
+            Limit::MaxCount { window, count } => {
+                outflows.iter().filter(|(time, _)| window.contains(*time, now)).count() >= count
+            },
+        })
+    }
+
+    // remember an accepted outflow, forgetting those no rule can see anymore
+    pub fn record(&mut self, rules: &[Rule], client: ClientId, currency: &str, amount: Decimal, now: u64) {
+        let retention = rules.iter().filter_map(|rule| match rule.limit {
+            Limit::MaxAmount(_) => None,
+            Limit::MaxTotal { window, .. } | Limit::MaxCount { window, .. } => Some(window.length()),
+        }).max();
+
+        let retention = match retention {
+            Some(retention) => retention,
+            None => return,
+        };
+
+        let outflows = self.outflows.entry((client, currency.to_string())).or_default();
+        outflows.push_back((now, amount));
+
+        while let Some(&(time, _)) = outflows.front() {
+            if time + retention > now {
+                break;
+            }
+
+            outflows.pop_front();
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use csv::StringRecord;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::io::Read;
+use std::path::Path;
+
+use crate::input::{Rows, Transactions};
+
+// the columns a Transaction can be read from
+pub const FIELDS: &[&str] = &["type", "client", "tx", "amount", "reason", "timestamp", "source", "currency", "to_currency"];
+
+// the columns every tx needs
+const REQUIRED: &[&str] = &["type", "client", "tx"];
+
+// how a partner's CSV columns map to tx fields
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Schema {
+    // input column name to field name, e.g. customer_id to client
+    pub rename: HashMap<String, String>,
+    // field name to a value used for every row, e.g. a currency the partner leaves out
+    pub fixed: HashMap<String, String>,
+    // input columns which are dropped rather than rejected
+    pub ignore: Vec<String>,
+    // the column names of a file without a header row, in order
+    pub columns: Option<Vec<String>>,
+}
+
+impl Schema {
+    // the field each input column is read into, None for ignored columns, checked against the
+    // passed header before any rows are read
+    pub fn fields(&self, header: &StringRecord) -> Result<Vec<Option<String>>, Box<dyn Error>> {
+        let mut fields = Vec::new();
+        let mut seen = HashSet::new();
+
+        for column in header.iter() {
+            if self.ignore.iter().any(|ignored| ignored == column) {
+                fields.push(None);
+                continue;
+            }
+
+            let field = self.rename.get(column).map(|field| field.as_str()).unwrap_or(column);
+
+            if !FIELDS.contains(&field) {
+                return Err(format!("Unknown column {}", column).into());
+            }
+
+            if self.fixed.contains_key(field) {
+                return Err(format!("Column {} is also a fixed value", column).into());
+            }
+
+            if !seen.insert(field) {
+                return Err(format!("More than one column is {}", field).into());
+            }
+
+            fields.push(Some(field.to_string()));
+        }
+
+        for column in self.rename.keys().chain(self.ignore.iter()) {
+            if !header.iter().any(|name| name == column) {
+                return Err(format!("Missing column {}", column).into());
+            }
+        }
+
+        for field in self.fixed.keys() {
+            if !FIELDS.contains(&field.as_str()) {
+                return Err(format!("Unknown fixed field {}", field).into());
+            }
+        }
+
+        for field in REQUIRED {
+            if !seen.contains(field) && !self.fixed.contains_key(*field) {
+                return Err(format!("No column for {}", field).into());
+            }
+        }
+
+        Ok(fields)
+    }
+
+    // read CSV txs through this schema, failing before any are read if the header doesn't match
+    pub fn read_transactions<'a, R: Read + 'a>(&self, reader: R) -> Result<Transactions<'a>, Box<dyn Error>> {
+        let (header, rows) = self.rows(reader)?;
+
+        Ok(Box::new(rows.map(move |row| Ok(row?.deserialize(Some(&header))?))))
+    }
+
+    // the header of the fields the rows are mapped to, and the mapped rows
+    pub(crate) fn rows<'a, R: Read + 'a>(&self, reader: R) -> Result<(StringRecord, Rows<'a>), Box<dyn Error>> {
+        let mut csv_reader = csv::ReaderBuilder::new()
+            .has_headers(self.columns.is_none())
+            .from_reader(reader);
+
+        let header = match &self.columns {
+            Some(columns) => StringRecord::from(columns.clone()),
+            None => csv_reader.headers()?.clone(),
+        };
+
+        let fields = self.fields(&header)?;
+
+        // the header the rows are deserialized with, the mapped columns then the fixed values
+        let fixed: Vec<(String, String)> = self.fixed.iter().map(|(field, value)| (field.clone(), value.clone())).collect();
+        let mapped: StringRecord = fields.iter()
+            .flatten()
+            .chain(fixed.iter().map(|(field, _)| field))
+            .collect();
+
+        let rows = csv_reader.into_records().map(move |result| {
+            let record = result?;
+
+            if record.len() != fields.len() {
+                return Err(format!("Row has {} columns, not {}", record.len(), fields.len()).into());
+            }
+
+            Ok(record.iter()
+                .zip(&fields)
+                .filter(|(_, field)| field.is_some())
+                .map(|(value, _)| value)
+                .chain(fixed.iter().map(|(_, value)| value.as_str()))
+                .collect())
+        });
+
+        Ok((mapped, Box::new(rows)))
+    }
+}
+
+// the schemas of each source, keyed by file name
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(transparent)]
+pub struct Schemas {
+    schemas: HashMap<String, Schema>,
+}
+
+impl Schemas {
+    // ctor
+    pub fn new() -> Schemas {
+        Default::default()
+    }
+
+    // load the schemas from a JSON object of file name to schema
+    pub fn load<R: Read>(reader: R) -> Result<Schemas, Box<dyn Error>> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    // add the schema of the passed file name or path, replacing any earlier one
+    pub fn insert(&mut self, source: &str, schema: Schema) {
+        self.schemas.insert(source.to_string(), schema);
+    }
+
+    // the schema of the passed input, by its whole path or just its file name
+    pub fn get(&self, path: &str) -> Option<&Schema> {
+        self.schemas.get(path).or_else(|| {
+            let name = Path::new(path).file_name()?.to_str()?;
+            self.schemas.get(name)
+        })
+    }
+}
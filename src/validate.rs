@@ -0,0 +1,136 @@
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+use crate::fx::minor_units;
+use crate::payments::{is_admin, Account, Outcome, PaymentError, Transaction};
+use crate::payments::{ADJUST, AUTHORIZE, CAPTURE, CHARGEBACK, CLOSE, DEPOSIT, DISPUTE, EXCHANGE, FREEZE, RESOLVE, RETURNED, SETTLE, UNLOCK, VOID, WITHDRAWAL};
+
+// txs pass these checks in order before they are applied:
+//
+// 1. schema: the tx type is known, admin tx types come from an admin input, the tx has the fields it
+//    needs, and its amount is positive, and in whole minor units of its currency for an exchange
+// 2. duplicate id: the tx id hasn't been used by a different tx, or burned
+// 3. account state: the account's status accepts the tx type
+// 4. funds: the account holds the currency of an outflow, and enough of it is available
+//
+// checks which need the tx referred to by a dispute, capture or settlement are left to the account
+
+// a tx which passed validation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Validated {
+    // AlreadyApplied for an exact replay, which mustn't be applied again
+    pub outcome: Outcome,
+    // the parsed amount, for tx types which carry one
+    pub amount: Option<Decimal>,
+}
+
+// true if the passed tx type has its own tx id, rather than referring to another tx
+pub fn owns_id(tx_type: &str) -> bool {
+    tx_type == DEPOSIT || tx_type == WITHDRAWAL || tx_type == AUTHORIZE || tx_type == EXCHANGE || is_admin(tx_type)
+}
+
+// check the tx type is known and parse the amount of tx types which carry one
+pub fn check_schema(tx: &Transaction) -> Result<Option<Decimal>, PaymentError> {
+    let parse = || match Decimal::from_str(&tx.amount) {
+        Ok(amt) => Ok(Some(amt)),
+        Err(_) => Err(PaymentError::BadDecimal)
+    };
+
+    // every amount but an adjustment's moves funds in the direction of the tx type
+    let positive = || match parse()? {
+        Some(amt) if amt <= Decimal::ZERO => Err(PaymentError::NonPositiveAmount),
+        amount => Ok(amount),
+    };
+
+    let tx_type = tx.tx_type.as_str();
+
+    if is_admin(tx_type) && !tx.admin {
+        return Err(PaymentError::AdminOnly);
+    }
+
+    if tx_type == DEPOSIT || tx_type == WITHDRAWAL || tx_type == AUTHORIZE {
+        positive()
+    } else if tx_type == ADJUST {
+        if tx.reason.is_empty() {
+            return Err(PaymentError::MissingReason);
+        }
+
+        // positive amounts are credits, negative amounts debits
+        parse()
+    } else if tx_type == EXCHANGE {
+        if tx.currency.is_empty() || tx.to_currency.is_empty() {
+            return Err(PaymentError::MissingCurrency);
+        }
+
+        // the debited amount has to be whole minor units of its currency, like the credited one
+        match positive()? {
+            Some(amt) if amt.round_dp(minor_units(&tx.currency)) != amt => Err(PaymentError::InexactAmount),
+            amount => Ok(amount),
+        }
+    } else if tx_type == CAPTURE {
+        // an empty amount captures the full authorization
+        if tx.amount.is_empty() {
+            return Ok(None);
+        }
+
+        positive()
+    } else if [DISPUTE, RESOLVE, CHARGEBACK, VOID, SETTLE, RETURNED, UNLOCK, FREEZE, CLOSE].contains(&tx_type) {
+        Ok(None)
+    } else {
+        Err(PaymentError::UnknownTxType)
+    }
+}
+
+// check the tx against the stored tx with the same id, if any, and whether its id was burned
+pub fn check_duplicate(tx: &Transaction, stored: Option<&Transaction>, burned: bool) -> Result<Outcome, PaymentError> {
+    if !owns_id(&tx.tx_type) {
+        return Ok(Outcome::Applied);
+    }
+
+    let stored = match stored {
+        Some(stored) => stored,
+        None if burned => return Err(PaymentError::TransactionIdBurned),
+        None => return Ok(Outcome::Applied),
+    };
+
+    let same_amount = match (Decimal::from_str(&stored.amount), Decimal::from_str(&tx.amount)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => stored.amount == tx.amount,
+    };
+
+    // an exact replay, e.g. after a network retry
+    if stored.tx_type == tx.tx_type && stored.client == tx.client && stored.currency == tx.currency
+        && stored.to_currency == tx.to_currency && same_amount {
+        Ok(Outcome::AlreadyApplied)
+    } else {
+        Err(PaymentError::DuplicateTransaction {
+            stored: Box::new(stored.clone()),
+            incoming: Box::new(tx.clone()),
+        })
+    }
+}
+
+// check the account's status accepts the tx type
+pub fn check_status(account: &Account, tx: &Transaction) -> Result<(), PaymentError> {
+    if account.status.accepts(&tx.tx_type) {
+        Ok(())
+    } else {
+        Err(account.status.rejection())
+    }
+}
+
+// check the account has the funds for an outflow, in the currency of the tx
+pub fn check_funds(account: &Account, tx: &Transaction, amount: Option<Decimal>) -> Result<(), PaymentError> {
+    let amount = match amount {
+        Some(amount) if tx.tx_type == WITHDRAWAL || tx.tx_type == AUTHORIZE || tx.tx_type == EXCHANGE => amount,
+        _ => return Ok(()),
+    };
+
+    if !tx.currency.is_empty() && !account.balances.contains_key(&tx.currency) {
+        Err(PaymentError::CurrencyNotHeld)
+    } else if account.balance(&tx.currency).available < amount {
+        Err(PaymentError::InsufficientFunds)
+    } else {
+        Ok(())
+    }
+}
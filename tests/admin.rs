@@ -0,0 +1,239 @@
+use rust_decimal_macros::dec;
+
+use payments::{Account, AccountStatus, PaymentError, Transaction, ADJUST, CHARGEBACK, DEPOSIT, DISPUTE, FREEZE, UNLOCK, WITHDRAWAL};
+
+mod helpers;
+
+use helpers::{cid, tid, make_ledger, make_disputed_ledger};
+
+#[test]
+fn freeze() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_ledger(client, tx, dec!(100.0));
+
+    let freeze = Transaction {
+        tx_type: FREEZE.to_string(),
+        client,
+        tx: tid(1),
+        admin: true,
+        ..Default::default()
+    };
+
+    ledger.process(&freeze).expect("Failed to process freeze");
+
+    let withdrawal = Transaction {
+        tx_type: WITHDRAWAL.to_string(),
+        client,
+        tx: tid(2),
+        amount: "10.00".to_string(),
+        ..Default::default()
+    };
+
+    match ledger.process(&withdrawal) {
+        Ok(_) => panic!("Withdrawal from frozen account succeeded"),
+        Err(err) => match err {
+            PaymentError::AccountFrozen => (),
+            _ => panic!("Withdrawal from frozen account failed with wrong error"),
+        }
+    }
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert_eq!(account.status, AccountStatus::Frozen);
+    assert_eq!(account.reason, FREEZE);
+    assert!(account.admin);
+}
+
+#[test]
+fn unlock_after_chargeback() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_disputed_ledger(client, tx, dec!(100.0));
+
+    let chargeback = Transaction {
+        tx_type: CHARGEBACK.to_string(),
+        client,
+        tx,
+        ..Default::default()
+    };
+
+    ledger.process(&chargeback).expect("Failed to process chargeback");
+
+    let unlock = Transaction {
+        tx_type: UNLOCK.to_string(),
+        client,
+        tx: tid(1),
+        admin: true,
+        ..Default::default()
+    };
+
+    ledger.process(&unlock).expect("Failed to process unlock");
+
+    let deposit = Transaction {
+        tx_type: DEPOSIT.to_string(),
+        client,
+        tx: tid(2),
+        amount: "20.00".to_string(),
+        ..Default::default()
+    };
+
+    ledger.process(&deposit).expect("Failed to deposit into unlocked account");
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert_eq!(account.status, AccountStatus::Active);
+    assert!(account.admin);
+    assert_eq!(account.balance("").available, dec!(20.0));
+    assert_eq!(account.balance("").total, dec!(20.0));
+}
+
+#[test]
+fn adjust_locked_account() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_ledger(client, tx, dec!(100.0));
+
+    ledger.accounts.get_mut(&client).expect("Failed to get account for client").status = AccountStatus::Locked;
+
+    let credit = Transaction {
+        tx_type: ADJUST.to_string(),
+        client,
+        tx: tid(1),
+        amount: "25.00".to_string(),
+        reason: "FEE_REFUND".to_string(),
+        admin: true,
+        ..Default::default()
+    };
+
+    ledger.process(&credit).expect("Failed to process credit adjustment");
+
+    let debit = Transaction {
+        tx_type: ADJUST.to_string(),
+        client,
+        tx: tid(2),
+        amount: "-5.00".to_string(),
+        reason: "FEE".to_string(),
+        admin: true,
+        ..Default::default()
+    };
+
+    ledger.process(&debit).expect("Failed to process debit adjustment");
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert_eq!(account.status, AccountStatus::Locked);
+    assert_eq!(account.balance("").available, dec!(120.0));
+    assert_eq!(account.balance("").total, dec!(120.0));
+}
+
+#[test]
+fn adjust_missing_reason() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_ledger(client, tx, dec!(100.0));
+
+    let adjust = Transaction {
+        tx_type: ADJUST.to_string(),
+        client,
+        tx: tid(1),
+        amount: "25.00".to_string(),
+        admin: true,
+        ..Default::default()
+    };
+
+    match ledger.process(&adjust) {
+        Ok(_) => panic!("Adjustment without reason succeeded"),
+        Err(err) => match err {
+            PaymentError::MissingReason => (),
+            _ => panic!("Adjustment without reason failed with wrong error"),
+        }
+    }
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert!(!account.admin);
+    assert_eq!(account.balance("").total, dec!(100.0));
+}
+
+#[test]
+fn adjust_not_disputable() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_ledger(client, tx, dec!(100.0));
+
+    let adjust = Transaction {
+        tx_type: ADJUST.to_string(),
+        client,
+        tx: tid(1),
+        amount: "25.00".to_string(),
+        reason: "GOODWILL".to_string(),
+        admin: true,
+        ..Default::default()
+    };
+
+    ledger.process(&adjust).expect("Failed to process adjustment");
+
+    let dispute = Transaction {
+        tx_type: DISPUTE.to_string(),
+        client,
+        tx: tid(1),
+        ..Default::default()
+    };
+
+    match ledger.process(&dispute) {
+        Ok(_) => panic!("Dispute of adjustment succeeded"),
+        Err(err) => match err {
+            PaymentError::NotDisputable => (),
+            _ => panic!("Dispute of adjustment failed with wrong error"),
+        }
+    }
+
+    let duplicate = Transaction {
+        tx_type: FREEZE.to_string(),
+        client,
+        tx: tid(1),
+        admin: true,
+        ..Default::default()
+    };
+
+    match ledger.process(&duplicate) {
+        Ok(_) => panic!("Duplicate admin transaction succeeded"),
+        Err(err) => match err {
+            PaymentError::DuplicateTransaction { .. } => (),
+            _ => panic!("Duplicate admin transaction failed with wrong error"),
+        }
+    }
+}
+
+#[test]
+fn unlock_without_admin_input() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_disputed_ledger(client, tx, dec!(100.0));
+
+    let chargeback = Transaction {
+        tx_type: CHARGEBACK.to_string(),
+        client,
+        tx,
+        ..Default::default()
+    };
+
+    ledger.process(&chargeback).expect("Failed to process chargeback");
+
+    // e.g. a row of a partner's input
+    let unlock = Transaction {
+        tx_type: UNLOCK.to_string(),
+        client,
+        tx: tid(1),
+        ..Default::default()
+    };
+
+    match ledger.process(&unlock) {
+        Ok(_) => panic!("Unlock from a non admin input succeeded"),
+        Err(err) => match err {
+            PaymentError::AdminOnly => (),
+            _ => panic!("Unlock from a non admin input failed with wrong error"),
+        }
+    }
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert_eq!(account.status, AccountStatus::Locked);
+    assert!(!account.admin);
+}
@@ -0,0 +1,121 @@
+use rust_decimal_macros::dec;
+
+use payments::{write_alerts_csv, write_alerts_json, Alert, AlertConfig, Config, Ledger, Structuring, Transaction, DEPOSIT, LARGE_DEPOSIT, LARGE_WITHDRAWAL, STRUCTURING, WITHDRAWAL};
+
+mod helpers;
+
+use helpers::{cid, tid};
+
+// a ledger which reports txs above 10000, and three deposits above 9000 within an hour
+fn make_alerting_ledger() -> Ledger {
+    let config = Config {
+        alerts: AlertConfig {
+            threshold: Some(dec!(10000)),
+            structuring: Some(Structuring { margin: dec!(0.1), count: 3, window: 3600 }),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    Ledger::new(config)
+}
+
+// process a deposit or withdrawal for client 0
+fn process(ledger: &mut Ledger, tx_type: &str, tx: u32, amount: &str, timestamp: u64) {
+    process_in(ledger, tx_type, tx, amount, "", timestamp);
+}
+
+// process a deposit or withdrawal for client 0 in the passed currency
+fn process_in(ledger: &mut Ledger, tx_type: &str, tx: u32, amount: &str, currency: &str, timestamp: u64) {
+    let transaction = Transaction {
+        tx_type: tx_type.to_string(),
+        client: cid(0),
+        tx: tid(tx),
+        amount: amount.to_string(),
+        currency: currency.to_string(),
+        timestamp: Some(timestamp),
+        ..Default::default()
+    };
+
+    ledger.process(&transaction).expect("Failed to process tx");
+}
+
+#[test]
+fn large_transactions() {
+    let mut ledger = make_alerting_ledger();
+
+    process(&mut ledger, DEPOSIT, 0, "10000.00", 0);
+    process(&mut ledger, DEPOSIT, 1, "10000.01", 0);
+    process(&mut ledger, WITHDRAWAL, 2, "15000", 0);
+
+    assert_eq!(ledger.alerts, vec![
+        Alert { client: cid(0), rule: LARGE_DEPOSIT.to_string(), currency: String::new(), txs: vec![tid(1)], amounts: vec![dec!(10000.01)] },
+        Alert { client: cid(0), rule: LARGE_WITHDRAWAL.to_string(), currency: String::new(), txs: vec![tid(2)], amounts: vec![dec!(15000)] },
+    ]);
+}
+
+#[test]
+fn structuring() {
+    let mut ledger = make_alerting_ledger();
+
+    process(&mut ledger, DEPOSIT, 0, "9500", 0);
+    process(&mut ledger, DEPOSIT, 1, "8999.99", 100);
+    process(&mut ledger, DEPOSIT, 2, "9900", 3000);
+    // too late for the first deposit
+    process(&mut ledger, DEPOSIT, 3, "9000", 3600);
+    assert!(ledger.alerts.is_empty());
+
+    process(&mut ledger, DEPOSIT, 4, "9999", 3700);
+
+    assert_eq!(ledger.alerts, vec![
+        Alert { client: cid(0), rule: STRUCTURING.to_string(), currency: String::new(), txs: vec![tid(2), tid(3), tid(4)], amounts: vec![dec!(9900), dec!(9000), dec!(9999)] },
+    ]);
+
+    let mut csv = vec![];
+    write_alerts_csv(&mut csv, &ledger.alerts).expect("Failed to write alerts");
+    assert_eq!(String::from_utf8(csv).expect("Bad utf8"), format!("client,rule,currency,txs,amounts\n{},structuring,,{};{};{},9900;9000;9999\n", cid(0), tid(2), tid(3), tid(4)));
+
+    let mut json = vec![];
+    write_alerts_json(&mut json, &ledger.alerts).expect("Failed to write alerts");
+    let value: serde_json::Value = serde_json::from_slice(&json).expect("Bad json");
+    assert_eq!(value[0]["txs"], serde_json::json!([tid(2), tid(3), tid(4)]));
+    assert_eq!(value[0]["amounts"], serde_json::json!(["9900", "9000", "9999"]));
+}
+
+#[test]
+fn currencies() {
+    let mut ledger = Ledger::new(Config {
+        alerts: AlertConfig {
+            threshold: Some(dec!(10000)),
+            thresholds: vec![("JPY".to_string(), dec!(1000000))].into_iter().collect(),
+            structuring: Some(Structuring { margin: dec!(0.1), count: 3, window: 3600 }),
+        },
+        ..Default::default()
+    });
+
+    // a JPY deposit is measured against the JPY threshold, not the default one
+    process_in(&mut ledger, DEPOSIT, 0, "50000", "JPY", 0);
+    process_in(&mut ledger, DEPOSIT, 1, "1000001", "JPY", 0);
+
+    // near-threshold deposits in different currencies don't add up to structuring
+    process_in(&mut ledger, DEPOSIT, 2, "9500", "USD", 0);
+    process_in(&mut ledger, DEPOSIT, 3, "9500", "USD", 0);
+    process_in(&mut ledger, DEPOSIT, 4, "9500", "EUR", 0);
+
+    assert_eq!(ledger.alerts, vec![
+        Alert { client: cid(0), rule: LARGE_DEPOSIT.to_string(), currency: "JPY".to_string(), txs: vec![tid(1)], amounts: vec![dec!(1000001)] },
+    ]);
+}
+
+#[test]
+fn load_alert_config() {
+    let json = r#"{ "threshold": "10000", "thresholds": { "JPY": "1000000" }, "structuring": { "margin": "0.1", "count": 3, "window": 86400 } }"#;
+
+    let config = AlertConfig::load(json.as_bytes()).expect("Failed to load alert config");
+
+    assert_eq!(config, AlertConfig {
+        threshold: Some(dec!(10000)),
+        structuring: Some(Structuring { margin: dec!(0.1), count: 3, window: 86400 }),
+        thresholds: vec![("JPY".to_string(), dec!(1000000))].into_iter().collect(),
+    });
+}
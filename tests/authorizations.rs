@@ -0,0 +1,177 @@
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use payments::{Account, Config, Ledger, Outcome, PaymentError, Transaction, AUTHORIZE, CAPTURE, DEPOSIT, VOID};
+
+mod helpers;
+
+use helpers::{cid, tid, make_ledger};
+
+// bootstrap a ledger with one client that has authorized part of a deposit
+fn make_authorized_ledger(mut ledger: Ledger, client: u16, tx: u32, amount: Decimal) -> Ledger {
+    let deposit = Transaction {
+        tx_type: DEPOSIT.to_string(),
+        client: cid(client),
+        tx: tid(tx),
+        amount: "100.00".to_string(),
+        ..Default::default()
+    };
+
+    ledger.process(&deposit).expect("Failed to process deposit");
+
+    let authorize = Transaction {
+        tx_type: AUTHORIZE.to_string(),
+        client: cid(client),
+        tx: tid(tx+1),
+        amount: amount.to_string(),
+        ..Default::default()
+    };
+
+    ledger.process(&authorize).expect("Failed to process authorization");
+
+    let account: &Account = ledger.accounts.get(&cid(client)).expect("Failed to get account for client");
+    assert_eq!(account.balance("").available, dec!(100.0) - amount);
+    assert_eq!(account.balance("").held, amount);
+    assert_eq!(account.balance("").total, dec!(100.0));
+
+    ledger
+}
+
+// process a capture or void of the passed authorization
+fn settle(ledger: &mut Ledger, tx_type: &str, client: u16, tx: u32, amount: &str) -> Result<Outcome, PaymentError> {
+    let settle = Transaction {
+        tx_type: tx_type.to_string(),
+        client: cid(client),
+        tx: tid(tx),
+        amount: amount.to_string(),
+        ..Default::default()
+    };
+
+    ledger.process(&settle)
+}
+
+#[test]
+fn full_capture() {
+    let mut ledger = make_authorized_ledger(Default::default(), 0, 0, dec!(40.0));
+
+    settle(&mut ledger, CAPTURE, 0, 1, "").expect("Failed to capture authorization");
+
+    let account: &Account = ledger.accounts.get(&cid(0)).expect("Failed to get account for client");
+    assert_eq!(account.balance("").available, dec!(60.0));
+    assert_eq!(account.balance("").held, dec!(0.0));
+    assert_eq!(account.balance("").total, dec!(60.0));
+
+    match settle(&mut ledger, VOID, 0, 1, "") {
+        Ok(_) => panic!("Void of captured authorization succeeded"),
+        Err(err) => match err {
+            PaymentError::AuthorizationNotFound => (),
+            _ => panic!("Void of captured authorization failed with wrong error"),
+        }
+    }
+}
+
+#[test]
+fn partial_capture() {
+    let mut ledger = make_authorized_ledger(Default::default(), 0, 0, dec!(40.0));
+
+    match settle(&mut ledger, CAPTURE, 0, 1, "50.00") {
+        Ok(_) => panic!("Capture beyond authorization succeeded"),
+        Err(err) => match err {
+            PaymentError::CaptureExceedsAuthorization => (),
+            _ => panic!("Capture beyond authorization failed with wrong error"),
+        }
+    }
+
+    // a negative capture would credit the account
+    for amount in &["-5.00", "0"] {
+        match settle(&mut ledger, CAPTURE, 0, 1, amount) {
+            Ok(_) => panic!("Capture of {} succeeded", amount),
+            Err(err) => match err {
+                PaymentError::NonPositiveAmount => (),
+                _ => panic!("Capture of {} failed with wrong error", amount),
+            }
+        }
+    }
+
+    settle(&mut ledger, CAPTURE, 0, 1, "25.00").expect("Failed to capture authorization");
+
+    let account: &Account = ledger.accounts.get(&cid(0)).expect("Failed to get account for client");
+    assert_eq!(account.balance("").available, dec!(75.0));
+    assert_eq!(account.balance("").held, dec!(0.0));
+    assert_eq!(account.balance("").total, dec!(75.0));
+}
+
+#[test]
+fn void() {
+    let mut ledger = make_authorized_ledger(Default::default(), 0, 0, dec!(40.0));
+
+    match settle(&mut ledger, VOID, 1, 1, "") {
+        Ok(_) => panic!("Void by wrong client succeeded"),
+        Err(err) => match err {
+            PaymentError::AuthorizationWrongClient => (),
+            _ => panic!("Void by wrong client failed with wrong error"),
+        }
+    }
+
+    settle(&mut ledger, VOID, 0, 1, "").expect("Failed to void authorization");
+
+    let account: &Account = ledger.accounts.get(&cid(0)).expect("Failed to get account for client");
+    assert_eq!(account.balance("").available, dec!(100.0));
+    assert_eq!(account.balance("").held, dec!(0.0));
+    assert_eq!(account.balance("").total, dec!(100.0));
+}
+
+#[test]
+fn expiry() {
+    let config = Config {
+        authorization_ttl: Some(2),
+        ..Default::default()
+    };
+    let mut ledger = make_authorized_ledger(Ledger::new(config), 0, 0, dec!(40.0));
+
+    // the authorization is still live one tx later
+    let deposit = Transaction {
+        tx_type: DEPOSIT.to_string(),
+        client: cid(1),
+        tx: tid(2),
+        amount: "1.00".to_string(),
+        ..Default::default()
+    };
+
+    ledger.process(&deposit).expect("Failed to process deposit");
+    assert_eq!(ledger.accounts.get(&cid(0)).expect("Failed to get account for client").balance("").held, dec!(40.0));
+
+    match settle(&mut ledger, CAPTURE, 0, 1, "") {
+        Ok(_) => panic!("Capture of expired authorization succeeded"),
+        Err(err) => match err {
+            PaymentError::AuthorizationNotFound => (),
+            _ => panic!("Capture of expired authorization failed with wrong error"),
+        }
+    }
+
+    let account: &Account = ledger.accounts.get(&cid(0)).expect("Failed to get account for client");
+    assert_eq!(account.balance("").available, dec!(100.0));
+    assert_eq!(account.balance("").held, dec!(0.0));
+    assert_eq!(account.balance("").total, dec!(100.0));
+}
+
+#[test]
+fn authorize_insufficient_funds() {
+    let mut ledger = make_ledger(cid(0), tid(0), dec!(100.0));
+
+    let authorize = Transaction {
+        tx_type: AUTHORIZE.to_string(),
+        client: cid(0),
+        tx: tid(1),
+        amount: "100.01".to_string(),
+        ..Default::default()
+    };
+
+    match ledger.process(&authorize) {
+        Ok(_) => panic!("Overdraft authorization succeeded"),
+        Err(err) => match err {
+            PaymentError::InsufficientFunds => (),
+            _ => panic!("Overdraft authorization failed with wrong error"),
+        }
+    }
+}
@@ -1,29 +1,33 @@
+// written before clippy was run on the tests
+#![allow(clippy::assertions_on_constants, clippy::redundant_field_names)]
+
 use rust_decimal_macros::dec;
 
-use payments::{Account, Ledger, PaymentError, Transaction, DEPOSIT, WITHDRAWAL};
+use payments::{Account, AccountStatus, Ledger, PaymentError, Transaction, DEPOSIT, WITHDRAWAL};
 
 mod helpers;
 
-use helpers::make_ledger;
+use helpers::{cid, tid, make_ledger};
 
 #[test]
 fn deposit() {
-    let client: u16 = 0;
-    let tx: u32 = 0;
+    let client = cid(0);
+    let tx = tid(0);
     let _ledger = make_ledger(client, tx, dec!(100.0));
 }
 
 #[test]
 fn withdrawal() {
-    let client: u16 = 0;
-    let tx: u32 = 0;
+    let client = cid(0);
+    let tx = tid(0);
     let mut ledger = make_ledger(client, tx, dec!(100.0));
     
     let withdrawal = Transaction {
         tx_type: WITHDRAWAL.to_string(),
         client: client,
-        tx: tx+1,
+        tx: tid(1),
         amount: "100.00".to_string(),
+        ..Default::default()
     };
 
     ledger.process(&withdrawal).expect("Failed to process withdrawal");
@@ -31,25 +35,26 @@ fn withdrawal() {
     assert_eq!(ledger.accounts.len(), 1);
 
     let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
-    assert_eq!(account.available, dec!(0.0));
-    assert_eq!(account.total, dec!(0.0));
+    assert_eq!(account.balance("").available, dec!(0.0));
+    assert_eq!(account.balance("").total, dec!(0.0));
 }
 
 #[test]
 fn insufficient_funds() {
-    let client: u16 = 0;
-    let tx: u32 = 0;
+    let client = cid(0);
+    let tx = tid(0);
     let mut ledger = make_ledger(client, tx, dec!(100.0));
     
     let withdrawal = Transaction {
         tx_type: WITHDRAWAL.to_string(),
         client: client,
-        tx: tx+1,
+        tx: tid(1),
         amount: "200.00".to_string(),
+        ..Default::default()
     };
     
     match ledger.process(&withdrawal) {
-        Ok(()) => assert!(false, "Overdraft withdrawal succeeded"),
+        Ok(_) => assert!(false, "Overdraft withdrawal succeeded"),
         Err(err) => match err {
             PaymentError::InsufficientFunds => (),
             _ => assert!(false, "Overdraft withdrawal failed with wrong error"),
@@ -58,8 +63,8 @@ fn insufficient_funds() {
 
     {
         let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client 0");
-        assert_eq!(account.available, dec!(100.0));
-        assert_eq!(account.total, dec!(100.0));
+        assert_eq!(account.balance("").available, dec!(100.0));
+        assert_eq!(account.balance("").total, dec!(100.0));
     }
 }
 
@@ -71,13 +76,14 @@ fn unknown_tx_type() {
     
     let depoosit = Transaction {
         tx_type: "depoosit".to_string(),
-        client: 0,
-        tx: 0,
+        client: cid(0),
+        tx: tid(0),
         amount: "100.00".to_string(),
+        ..Default::default()
     };
 
     match ledger.process(&depoosit) {
-        Ok(()) => assert!(false, "Unknown tx type succeeded"),
+        Ok(_) => assert!(false, "Unknown tx type succeeded"),
         Err(err) => match err {
             PaymentError::UnknownTxType => (),
             _ => assert!(false, "Unknown tx type failed with wrong error"),
@@ -93,13 +99,14 @@ fn bad_decimal() {
     
     let deposit = Transaction {
         tx_type: DEPOSIT.to_string(),
-        client: 0,
-        tx: 0,
+        client: cid(0),
+        tx: tid(0),
         amount: "ABCDE".to_string(),
+        ..Default::default()
     };
 
     match ledger.process(&deposit) {
-        Ok(()) => assert!(false, "Bad amount tx succeeded"),
+        Ok(_) => assert!(false, "Bad amount tx succeeded"),
         Err(err) => match err {
             PaymentError::BadDecimal => (),
             _ => assert!(false, "Bad amount tx failed with wrong error"),
@@ -109,8 +116,8 @@ fn bad_decimal() {
 
 #[test]
 fn duplicate_tx() {
-    let client: u16 = 0;
-    let tx: u32 = 0;
+    let client = cid(0);
+    let tx = tid(0);
     let mut ledger = make_ledger(client, tx, dec!(100.0));
 
     let withdrawal = Transaction {
@@ -118,12 +125,13 @@ fn duplicate_tx() {
         client: client,
         tx: tx,
         amount: "2.00".to_string(),
+        ..Default::default()
     };
     
     match ledger.process(&withdrawal) {
-        Ok(()) => assert!(false, "Duplicate transaction succeeded"),
+        Ok(_) => assert!(false, "Duplicate transaction succeeded"),
         Err(err) => match err {
-            PaymentError::DuplicateTransaction => (),
+            PaymentError::DuplicateTransaction { .. } => (),
             _ => assert!(false, "Duplicate transaction failed with wrong error"),
         }
     }
@@ -131,24 +139,25 @@ fn duplicate_tx() {
 
 #[test]
 fn account_locked() {
-    let client: u16 = 0;
-    let tx: u32 = 0;
+    let client = cid(0);
+    let tx = tid(0);
     let mut ledger = make_ledger(client, tx, dec!(100.0));
 
     {
         let account: &mut Account = ledger.accounts.get_mut(&client).expect("Failed to get account for client");
-        account.locked = true;
+        account.status = AccountStatus::Locked;
     }
 
     let withdrawal = Transaction {
         tx_type: WITHDRAWAL.to_string(),
-        client: 0,
-        tx: 0,
+        client: cid(0),
+        tx: tid(1),
         amount: "2.00".to_string(),
+        ..Default::default()
     };
     
     match ledger.process(&withdrawal) {
-        Ok(()) => assert!(false, "Account locked but tx succeeded"),
+        Ok(_) => assert!(false, "Account locked but tx succeeded"),
         Err(err) => match err {
             PaymentError::AccountLocked => (),
             _ => assert!(false, "Account locked but tx failed with wrong error"),
@@ -0,0 +1,96 @@
+use rust_decimal_macros::dec;
+
+use payments::{Batch, Ledger, Schema, Schemas, Transaction};
+
+mod helpers;
+
+use helpers::{cid, tid};
+
+// a CSV row of client n's tx n
+fn row(tx_type: &str, n: u16, amount: &str) -> String {
+    format!("{},{},{},{}\n", tx_type, cid(n), tid(n.into()), amount)
+}
+
+// read a whole batch, and the result of checking it against its trailer
+fn read(data: &str, schema: Option<&Schema>) -> (Vec<Transaction>, Result<(), String>) {
+    let mut batch = Batch::read(data.as_bytes(), schema).expect("Failed to read header");
+    let txs = batch.by_ref().collect::<Result<_, _>>().expect("Failed to read txs");
+
+    (txs, batch.verify())
+}
+
+#[test]
+fn matching_trailer() {
+    let (txs, verified) = read(&format!("type,client,tx,amount\nheader,,20240102,\n{}{}{}trailer,,3,15.50\n", row("deposit", 1, "10"), row("dispute", 1, ""), row("deposit", 2, "5.5")), None);
+
+    assert_eq!(txs.len(), 3);
+    assert_eq!(verified, Ok(()));
+}
+
+#[test]
+fn mismatches() {
+    let (_, verified) = read(&format!("type,client,tx,amount\n{}{}", row("deposit", 1, "10"), row("deposit", 2, "5.5")), None);
+    assert_eq!(verified, Err("No trailer, the file may be truncated".to_string()));
+
+    let (_, verified) = read(&format!("type,client,tx,amount\n{}trailer,,2,10\n", row("deposit", 1, "10")), None);
+    assert_eq!(verified, Err("Trailer count 2 doesn't match the 1 txs read".to_string()));
+
+    let (_, verified) = read(&format!("type,client,tx,amount\n{}trailer,,1,10.01\n", row("deposit", 1, "10")), None);
+    assert_eq!(verified, Err("Trailer total 10.01 doesn't match the 10 read".to_string()));
+
+    let mut batch = Batch::read("type,client,tx,amount\ntrailer,,0,0\ndeposit,1,1,10\n".as_bytes(), None).expect("Failed to read header");
+    assert!(batch.next().expect("Failed to read row").is_err());
+
+    let mut batch = Batch::read("type,client,tx,amount\ntrailer,,two,0\n".as_bytes(), None).expect("Failed to read header");
+    assert!(batch.next().expect("Failed to read row").is_err());
+}
+
+#[test]
+fn mapped_columns() {
+    let schemas = Schemas::load(r#"{"partner.csv": {"rename": {"kind": "type", "customer": "client", "ref": "tx", "value": "amount"}}}"#.as_bytes())
+        .expect("Failed to load schemas");
+
+    let (txs, verified) = read(&format!("kind,customer,ref,value\n{}trailer,,1,2.5\n", row("deposit", 1, "2.5")), schemas.get("partner.csv"));
+
+    assert_eq!(txs[0].amount, "2.5");
+    assert_eq!(verified, Ok(()));
+}
+
+#[test]
+fn staged() {
+    let data = format!("type,client,tx,amount\n{}{}", row("deposit", 1, "10"), row("deposit", 2, "5"));
+    let batch = Batch::read(data.as_bytes(), None).expect("Failed to read header");
+
+    match batch.staged() {
+        Ok(_) => panic!("File without a trailer was staged"),
+        Err(err) => assert_eq!(err.to_string(), "No trailer, the file may be truncated"),
+    }
+
+    let data = format!("{}trailer,,2,15\n", data);
+    let batch = Batch::read(data.as_bytes(), None).expect("Failed to read header");
+    let txs = batch.staged().expect("Failed to stage file");
+
+    let mut ledger: Ledger = Default::default();
+
+    for tx in &txs {
+        ledger.process(tx).expect("Failed to process tx");
+    }
+
+    assert_eq!(ledger.accounts[&cid(1)].balance("").total, dec!(10));
+    assert_eq!(ledger.accounts[&cid(2)].balance("").total, dec!(5));
+}
+
+#[test]
+fn check() {
+    let data = format!("type,client,tx,amount\n{}{}trailer,,2,14\n", row("deposit", 1, "10"), row("deposit", 2, "5"));
+    let batch = Batch::read(data.as_bytes(), None).expect("Failed to read header");
+
+    match batch.check() {
+        Ok(_) => panic!("File with the wrong total was checked"),
+        Err(err) => assert_eq!(err.to_string(), "Trailer total 14 doesn't match the 15 read"),
+    }
+
+    let data = data.replace("trailer,,2,14", "trailer,,2,15");
+    let batch = Batch::read(data.as_bytes(), None).expect("Failed to read header");
+    batch.check().expect("Failed to check file");
+}
@@ -0,0 +1,82 @@
+// the fixture's entry references are integer tx ids, which aren't uuids
+#![cfg(not(feature = "uuid-ids"))]
+
+use rust_decimal_macros::dec;
+use std::fs::File;
+use std::io::BufReader;
+
+use payments::{import_camt053, parse_date, ClientMap, Import, Ledger, DEPOSIT, WITHDRAWAL};
+
+fn make_client_map() -> ClientMap {
+    let data = "reference,client\nDE00CLIENT0000000001,1\nCLIENT-2,2\nVIRTUAL-3,3\n";
+
+    ClientMap::load(data.as_bytes()).expect("Failed to load client map")
+}
+
+fn import() -> Import {
+    let file = File::open("tests/fixtures/camt053.xml").expect("Failed to open statement fixture");
+
+    import_camt053(BufReader::new(file), &make_client_map()).expect("Failed to import statement")
+}
+
+#[test]
+fn entries() {
+    let import = import();
+
+    assert_eq!(import.transactions.len(), 3);
+
+    let deposit = &import.transactions[0];
+    assert_eq!(deposit.tx_type, DEPOSIT);
+    assert_eq!(deposit.client, 1);
+    assert_eq!(deposit.tx, 1001);
+    assert_eq!(deposit.amount, "100.50");
+    assert_eq!(deposit.currency, "EUR");
+    assert_eq!(deposit.timestamp, Some(1704153600));
+
+    let withdrawal = &import.transactions[1];
+    assert_eq!(withdrawal.tx_type, WITHDRAWAL);
+    assert_eq!(withdrawal.client, 2);
+    assert_eq!(withdrawal.tx, 1002);
+
+    // no counterparty, so the statement's account is used
+    let virtual_deposit = &import.transactions[2];
+    assert_eq!(virtual_deposit.client, 3);
+    assert_eq!(virtual_deposit.currency, "USD");
+    assert_eq!(virtual_deposit.timestamp, Some(1704240000));
+}
+
+#[test]
+fn unmapped() {
+    let import = import();
+    let unmapped: Vec<(&str, &str, &str)> = import.unmapped.iter()
+        .map(|u| (u.reference.as_str(), u.account.as_str(), u.reason.as_str()))
+        .collect();
+
+    assert_eq!(unmapped, vec![
+        ("1003", "", "entry not booked"),
+        ("1004", "FR00UNKNOWN000000000", "no client for account"),
+        ("ABC", "VIRTUAL-3", "entry reference is not a tx id"),
+    ]);
+}
+
+#[test]
+fn into_ledger() {
+    let mut ledger: Ledger = Default::default();
+
+    for tx in &import().transactions {
+        if let Err(err) = ledger.process(tx) {
+            assert_eq!(tx.tx, 1002, "Unexpected error {} for tx {}", err, tx.tx);
+        }
+    }
+
+    assert_eq!(ledger.accounts.get(&1).expect("Failed to get account for client 1").balance("EUR").available, dec!(100.50));
+    assert_eq!(ledger.accounts.get(&3).expect("Failed to get account for client 3").balance("USD").available, dec!(9.99));
+}
+
+#[test]
+fn dates() {
+    assert_eq!(parse_date("1970-01-01"), Some(0));
+    assert_eq!(parse_date("2024-02-29T12:00:00"), Some(1709164800));
+    assert_eq!(parse_date("2024-13-01"), None);
+    assert_eq!(parse_date("20240101"), None);
+}
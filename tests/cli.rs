@@ -0,0 +1,184 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+use payments::WIDE_IDS;
+
+mod helpers;
+
+use helpers::{cid, tid};
+
+// a deposit by client 1
+fn csv() -> String {
+    format!("type,client,tx,amount\ndeposit,{},{},1.5\n", cid(1), tid(1))
+}
+
+// client 1's account after the deposit
+fn accounts() -> String {
+    format!("client,currency,available,held,pending,total,locked,status,reason,admin\n{},,1.5000,0.0000,0.0000,1.5000,false,active,,false\n", cid(1))
+}
+
+// run the binary with the passed args, writing the passed data to its stdin
+fn run(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_payments"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run payments");
+
+    // the binary may exit without reading stdin, e.g. on a bad option
+    let _ = child.stdin.take().expect("Failed to open stdin").write_all(stdin);
+
+    child.wait_with_output().expect("Failed to wait for payments")
+}
+
+#[test]
+fn stdin_without_files() {
+    let output = run(&[], csv().as_bytes());
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), accounts());
+}
+
+#[test]
+fn stdin_dash() {
+    let output = run(&["--scale", "4", "-"], csv().as_bytes());
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), accounts());
+}
+
+#[test]
+fn unknown_option() {
+    let output = run(&["--bogus"], csv().as_bytes());
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Unknown option --bogus"));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn files_after_separator() {
+    let output = run(&["--", "--missing"], b"");
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Error reading records from --missing"));
+}
+
+#[test]
+fn trailer_rollback() {
+    let output = run(&["--trailers"], format!("{}deposit,{},{},2.0\ntrailer,,3,3.5\n", csv(), cid(1), tid(2)).as_bytes());
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Trailer count 3 doesn't match the 2 txs read, none of its txs were processed"));
+    assert!(output.stdout.is_empty());
+
+    let output = run(&["--trailers"], format!("{}trailer,,1,1.50\n", csv()).as_bytes());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), accounts());
+}
+
+#[test]
+fn trailer_rollback_file() {
+    let path = env::temp_dir().join(format!("payments-trailer-{}.csv", std::process::id()));
+    let file = path.to_str().expect("Bad temp path");
+
+    // files are checked in a first pass and processed in a second
+    fs::write(&path, format!("{}trailer,,2,1.5\n", csv())).expect("Failed to write input");
+    let output = run(&["--trailers", file], b"");
+
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Trailer count 2 doesn't match the 1 txs read, none of its txs were processed"));
+    assert!(output.stdout.is_empty());
+
+    fs::write(&path, format!("{}trailer,,1,1.5\n", csv())).expect("Failed to write input");
+    let output = run(&["--trailers", file], b"");
+    fs::remove_file(&path).expect("Failed to remove input");
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), accounts());
+}
+
+// the fixture has integer ids
+#[cfg(not(feature = "uuid-ids"))]
+#[test]
+fn source_is_canonical_path() {
+    let output = run(&["--id-scope", "source", "tests/fixtures/deposit.csv", "tests/../tests/fixtures/deposit.csv"], b"");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), accounts());
+}
+
+#[test]
+fn admin_input() {
+    let unlock = format!("type,client,tx,amount\nunlock,{},{},\n", cid(1), tid(2));
+    let path = env::temp_dir().join(format!("payments-admin-{}.csv", std::process::id()));
+    fs::write(&path, unlock).expect("Failed to write admin input");
+
+    // an admin tx in the normal input is rejected
+    let output = run(&["-"], format!("{}freeze,{},{},\n", csv(), cid(1), tid(2)).as_bytes());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("AdminOnly"));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), accounts());
+
+    let output = run(&["--admin-input", path.to_str().expect("Bad temp path"), "-"], csv().as_bytes());
+    fs::remove_file(&path).expect("Failed to remove admin input");
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with(",false,active,,true\n"));
+}
+
+#[test]
+fn spread_needs_house_account() {
+    let output = run(&["--rates", "tests/fixtures/rates.csv"], csv().as_bytes());
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("--rates with a spread requires --house-account"));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn nacha_ids() {
+    let output = run(&["--input-format", "nacha", "tests/fixtures/nacha.ach"], b"");
+
+    // trace numbers only make unique tx ids within their originating bank without wide ids
+    if WIDE_IDS {
+        assert!(output.status.success());
+    } else {
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).starts_with("NACHA inputs require --id-scope source"));
+    }
+
+    let output = run(&["--id-scope", "source", "tests/fixtures/nacha.ach"], b"");
+    assert!(output.status.success());
+}
+
+#[test]
+fn payout_traces() {
+    let dir = env::temp_dir().join(format!("payments-traces-{}", std::process::id()));
+    fs::create_dir_all(&dir).expect("Failed to create temp dir");
+
+    let path = |name: &str| dir.join(name).to_str().expect("Bad temp path").to_string();
+    fs::write(path("payees.csv"), format!("client,name,account,bank\n{},Alice,11111111,021000021\n", cid(1))).expect("Failed to write payees");
+    fs::write(path("originator.json"), r#"{"name": "Payments Inc", "bank": "091000019", "currency": "USD"}"#).expect("Failed to write originator");
+
+    let args = ["--payouts", &path("payouts.ach"), "--payees", &path("payees.csv"), "--originator", &path("originator.json")];
+    let withdrawal = format!("{}withdrawal,{},{},1.0\n", csv(), cid(1), tid(2));
+
+    let output = run(&args, withdrawal.as_bytes());
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("NACHA --payouts require --traces"));
+
+    // each run's payouts carry on from the trace numbers of the last
+    for sequence in &["0000001", "0000002"] {
+        let output = run(&[&args[..], &["--traces", &path("traces.csv")]].concat(), withdrawal.as_bytes());
+        assert!(output.status.success());
+
+        let payouts = fs::read_to_string(path("payouts.ach")).expect("Failed to read payouts");
+        assert!(payouts.lines().nth(2).expect("No entry").ends_with(&format!("09100001{}", sequence)));
+    }
+
+    let traces = fs::read_to_string(path("traces.csv")).expect("Failed to read traces");
+    fs::remove_dir_all(&dir).expect("Failed to remove temp dir");
+
+    assert_eq!(traces, format!("trace,client,tx,source\n091000010000001,{0},{1},-\n091000010000002,{0},{1},-\n", cid(1), tid(2)));
+}
@@ -0,0 +1,62 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+use payments::decompress;
+#[cfg(not(feature = "uuid-ids"))]
+use payments::{read_transactions, ClientMap, InputFormat, TraceMap, Transaction};
+
+const CSV: &str = "type,client,tx,amount\ndeposit,1,1,1.0\ndeposit,1,2,2.0\n";
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(data).expect("Failed to compress");
+    encoder.finish().expect("Failed to finish compressing")
+}
+
+fn read_all(data: &[u8]) -> String {
+    let mut output = String::new();
+    decompress(data).expect("Failed to detect compression").read_to_string(&mut output).expect("Failed to decompress");
+    output
+}
+
+#[test]
+fn plain() {
+    assert_eq!(read_all(CSV.as_bytes()), CSV);
+    assert_eq!(read_all(b""), "");
+}
+
+#[test]
+fn gzip_members() {
+    assert_eq!(read_all(&gzip(CSV.as_bytes())), CSV);
+
+    // concatenated members, as written by appending to a .gz file
+    let (head, tail) = CSV.split_at(30);
+    let mut data = gzip(head.as_bytes());
+    data.extend(gzip(tail.as_bytes()));
+    assert_eq!(read_all(&data), CSV);
+}
+
+#[test]
+fn zstd() {
+    let data = zstd::encode_all(CSV.as_bytes(), 0).expect("Failed to compress");
+    assert_eq!(read_all(&data), CSV);
+}
+
+// JSON ids are numbers, which aren't uuids
+#[cfg(not(feature = "uuid-ids"))]
+#[test]
+fn compressed_json_lines() {
+    let data = zstd::encode_all(&b"{\"type\":\"deposit\",\"client\":1,\"tx\":1,\"amount\":1.5}\n"[..], 0).expect("Failed to compress");
+
+    assert_eq!(InputFormat::detect("txs.jsonl.zst"), InputFormat::JsonLines);
+    assert_eq!(InputFormat::detect("txs.csv.gz"), InputFormat::Csv);
+
+    let reader = decompress(&data[..]).expect("Failed to detect compression");
+    let txs: Vec<Transaction> = read_transactions(reader, InputFormat::JsonLines, &ClientMap::new(), &TraceMap::new())
+        .collect::<Result<_, _>>()
+        .expect("Failed to read txs");
+
+    assert_eq!(txs.len(), 1);
+    assert_eq!(txs[0].amount, "1.5");
+}
@@ -0,0 +1,133 @@
+use rust_decimal_macros::dec;
+
+use payments::{Account, AccountRecord, ClientId, Ledger, PaymentError, Transaction, TxId, DEPOSIT, DISPUTE, WITHDRAWAL};
+
+mod helpers;
+
+use helpers::{cid, tid};
+
+// bootstrap a ledger with one client that holds USD and EUR
+fn make_currency_ledger(client: ClientId, tx: TxId) -> Ledger {
+    let mut ledger: Ledger = Default::default();
+
+    for (id, currency, amount) in &[(tx, "USD", "100.0"), (tid(1), "EUR", "50.0")] {
+        let deposit = Transaction {
+            tx_type: DEPOSIT.to_string(),
+            client,
+            tx: *id,
+            amount: amount.to_string(),
+            currency: currency.to_string(),
+            ..Default::default()
+        };
+
+        ledger.process(&deposit).expect("Failed to process deposit");
+    }
+
+    ledger
+}
+
+#[test]
+fn balances() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_currency_ledger(client, tx);
+
+    let withdrawal = Transaction {
+        tx_type: WITHDRAWAL.to_string(),
+        client,
+        tx: tid(2),
+        amount: "80.0".to_string(),
+        currency: "EUR".to_string(),
+        ..Default::default()
+    };
+
+    match ledger.process(&withdrawal) {
+        Ok(_) => panic!("Withdrawal of more EUR than available succeeded"),
+        Err(err) => match err {
+            PaymentError::InsufficientFunds => (),
+            _ => panic!("Withdrawal of more EUR than available failed with wrong error"),
+        }
+    }
+
+    let withdrawal = Transaction {
+        amount: "20.0".to_string(),
+        ..withdrawal
+    };
+
+    ledger.process(&withdrawal).expect("Failed to process EUR withdrawal");
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert_eq!(account.balance("USD").available, dec!(100.0));
+    assert_eq!(account.balance("EUR").available, dec!(30.0));
+    assert_eq!(account.balance("EUR").total, dec!(30.0));
+    assert_eq!(account.balance("").total, dec!(0.0));
+}
+
+#[test]
+fn currency_not_held() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_currency_ledger(client, tx);
+
+    let withdrawal = Transaction {
+        tx_type: WITHDRAWAL.to_string(),
+        client,
+        tx: tid(2),
+        amount: "1.0".to_string(),
+        currency: "GBP".to_string(),
+        ..Default::default()
+    };
+
+    match ledger.process(&withdrawal) {
+        Ok(_) => panic!("Withdrawal in currency not held succeeded"),
+        Err(err) => match err {
+            PaymentError::CurrencyNotHeld => (),
+            _ => panic!("Withdrawal in currency not held failed with wrong error"),
+        }
+    }
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert!(!account.balances.contains_key("GBP"));
+}
+
+#[test]
+fn dispute_in_original_currency() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_currency_ledger(client, tx);
+
+    // disputes carry no currency of their own
+    let dispute = Transaction {
+        tx_type: DISPUTE.to_string(),
+        client,
+        tx: tid(1),
+        ..Default::default()
+    };
+
+    ledger.process(&dispute).expect("Failed to process dispute");
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert_eq!(account.balance("EUR").available, dec!(0.0));
+    assert_eq!(account.balance("EUR").held, dec!(50.0));
+    assert_eq!(account.balance("EUR").total, dec!(50.0));
+    assert_eq!(account.balance("USD").held, dec!(0.0));
+    assert!(!account.balances.contains_key(""));
+}
+
+#[test]
+fn output_rows() {
+    let client = cid(0);
+    let tx = tid(0);
+    let ledger = make_currency_ledger(client, tx);
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+
+    let mut writer = csv::Writer::from_writer(vec![]);
+    for record in AccountRecord::all(account, false) {
+        writer.serialize(record).expect("Failed to serialize account");
+    }
+    let output = String::from_utf8(writer.into_inner().expect("Failed to flush csv")).expect("Bad utf8");
+    assert_eq!(output, format!("client,currency,available,held,pending,total,locked,status,reason,admin\n\
+                                {0},EUR,50.0,0.0,0.0,50.0,false,active,,false\n\
+                                {0},USD,100.0,0.0,0.0,100.0,false,active,,false\n", client));
+}
@@ -1,22 +1,25 @@
+// written before clippy was run on the tests
+#![allow(clippy::assertions_on_constants, clippy::redundant_field_names)]
+
 use rust_decimal_macros::dec;
 
-use payments::{Account, PaymentError, Transaction, WITHDRAWAL, DISPUTE, RESOLVE, CHARGEBACK};
+use payments::{Account, AccountStatus, PaymentError, Transaction, WITHDRAWAL, DISPUTE, RESOLVE, CHARGEBACK};
 
 mod helpers;
 
-use helpers::{make_ledger, make_disputed_ledger};
+use helpers::{cid, tid, make_ledger, make_disputed_ledger};
 
 #[test]
 fn dispute_deposit() {
-    let client: u16 = 0;
-    let tx: u32 = 0;
+    let client = cid(0);
+    let tx = tid(0);
     let _ledger = make_disputed_ledger(client, tx, dec!(100.0));
 }
     
 #[test]
 fn resolve_deposit() {
-    let client: u16 = 0;
-    let tx: u32 = 0;
+    let client = cid(0);
+    let tx = tid(0);
     let mut ledger = make_disputed_ledger(client, tx, dec!(100.0));
     
     let resolve = Transaction {
@@ -24,6 +27,7 @@ fn resolve_deposit() {
         client: client,
         tx: tx,
         amount: "".to_string(),
+        ..Default::default()
     };
 
     ledger.process(&resolve).expect("Failed to process resolve");
@@ -31,15 +35,15 @@ fn resolve_deposit() {
     assert_eq!(ledger.accounts.len(), 1);
 
     let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
-    assert_eq!(account.available, dec!(100.0));
-    assert_eq!(account.held, dec!(0.0));
-    assert_eq!(account.total, dec!(100.0));
+    assert_eq!(account.balance("").available, dec!(100.0));
+    assert_eq!(account.balance("").held, dec!(0.0));
+    assert_eq!(account.balance("").total, dec!(100.0));
 }
 
 #[test]
 fn chargeback_deposit() {
-    let client: u16 = 0;
-    let tx: u32 = 0;
+    let client = cid(0);
+    let tx = tid(0);
     let mut ledger = make_disputed_ledger(client, tx, dec!(100.0));
     
     let chargeback = Transaction {
@@ -47,6 +51,7 @@ fn chargeback_deposit() {
         client: client,
         tx: tx,
         amount: "".to_string(),
+        ..Default::default()
     };
 
     ledger.process(&chargeback).expect("Failed to process chargeback");
@@ -54,15 +59,15 @@ fn chargeback_deposit() {
     assert_eq!(ledger.accounts.len(), 1);
 
     let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
-    assert_eq!(account.available, dec!(0.0));
-    assert_eq!(account.held, dec!(0.0));
-    assert_eq!(account.total, dec!(0.0));
+    assert_eq!(account.balance("").available, dec!(0.0));
+    assert_eq!(account.balance("").held, dec!(0.0));
+    assert_eq!(account.balance("").total, dec!(0.0));
 }
 
 #[test]
 fn already_disputed() {
-    let client: u16 = 0;
-    let tx: u32 = 0;
+    let client = cid(0);
+    let tx = tid(0);
     let mut ledger = make_disputed_ledger(client, tx, dec!(100.0));
     
     let dispute = Transaction {
@@ -70,10 +75,11 @@ fn already_disputed() {
         client: client,
         tx: tx,
         amount: "".to_string(),
+        ..Default::default()
     };
 
     match ledger.process(&dispute) {
-        Ok(()) => assert!(false, "Double dispute succeeded"),
+        Ok(_) => assert!(false, "Double dispute succeeded"),
         Err(err) => match err {
             PaymentError::AlreadyDisputed => (),
             _ => assert!(false, "Double dispute failed with wrong error"),
@@ -82,14 +88,14 @@ fn already_disputed() {
 
     {
         let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client 0");
-        assert_eq!(account.available, dec!(0.0));
+        assert_eq!(account.balance("").available, dec!(0.0));
     }
 }
 
 #[test]
 fn not_disputed() {
-    let client: u16 = 0;
-    let tx: u32 = 0;
+    let client = cid(0);
+    let tx = tid(0);
     let mut ledger = make_disputed_ledger(client, tx, dec!(100.0));
     
     let resolve = Transaction {
@@ -97,6 +103,7 @@ fn not_disputed() {
         client: client,
         tx: tx,
         amount: "".to_string(),
+        ..Default::default()
     };
 
     ledger.process(&resolve).expect("Failed to process resolve");
@@ -104,11 +111,11 @@ fn not_disputed() {
     assert_eq!(ledger.accounts.len(), 1);
 
     let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
-    assert_eq!(account.available, dec!(100.0));
-    assert_eq!(account.held, dec!(0.0));
+    assert_eq!(account.balance("").available, dec!(100.0));
+    assert_eq!(account.balance("").held, dec!(0.0));
 
     match ledger.process(&resolve) {
-        Ok(()) => assert!(false, "Not disputed succeeded"),
+        Ok(_) => assert!(false, "Not disputed succeeded"),
         Err(err) => match err {
             PaymentError::NotDisputed => (),
             _ => assert!(false, "Not disputed failed with wrong error"),
@@ -119,15 +126,16 @@ fn not_disputed() {
 
 #[test]
 fn disputed_wrong_client() {
-    let client: u16 = 0;
-    let tx: u32 = 0;
+    let client = cid(0);
+    let tx = tid(0);
     let mut ledger = make_ledger(client, tx, dec!(100.0));
     
     let deposit = Transaction {
         tx_type: "deposit".to_string(),
-        client: client+1,
-        tx: tx+1,
+        client: cid(1),
+        tx: tid(1),
         amount: "100.00".to_string(),
+        ..Default::default()
     };
 
     ledger.process(&deposit).expect("Failed to process deposit");
@@ -136,13 +144,14 @@ fn disputed_wrong_client() {
 
     let dispute = Transaction {
         tx_type: DISPUTE.to_string(),
-        client: client+1,
+        client: cid(1),
         tx: tx,
         amount: "".to_string(),
+        ..Default::default()
     };
 
     match ledger.process(&dispute) {
-        Ok(()) => assert!(false, "Disputed wrong client succeeded"),
+        Ok(_) => assert!(false, "Disputed wrong client succeeded"),
         Err(err) => match err {
             PaymentError::DisputedWrongClient => (),
             _ => assert!(false, "Disputed wrong client failed with wrong error"),
@@ -152,19 +161,20 @@ fn disputed_wrong_client() {
 
 #[test]
 fn disputed_tx_not_found() {
-    let client: u16 = 0;
-    let tx: u32 = 0;
+    let client = cid(0);
+    let tx = tid(0);
     let mut ledger = make_ledger(client, tx, dec!(100.0));
     
     let dispute = Transaction {
         tx_type: DISPUTE.to_string(),
         client: client,
-        tx: tx+1,
+        tx: tid(1),
         amount: "".to_string(),
+        ..Default::default()
     };
 
     match ledger.process(&dispute) {
-        Ok(()) => assert!(false, "Disputed tx not found succeeded"),
+        Ok(_) => assert!(false, "Disputed tx not found succeeded"),
         Err(err) => match err {
             PaymentError::DisputedTxNotFound => (),
             _ => assert!(false, "Disputed tx not found failed with wrong error"),
@@ -174,15 +184,16 @@ fn disputed_tx_not_found() {
 
 #[test]
 fn dispute_withdrawal() {
-    let client: u16 = 0;
-    let tx: u32 = 0;
+    let client = cid(0);
+    let tx = tid(0);
     let mut ledger = make_ledger(client, tx, dec!(100.0));
     
     let withdrawal = Transaction {
         tx_type: WITHDRAWAL.to_string(),
         client: client,
-        tx: tx+1,
+        tx: tid(1),
         amount: "50.00".to_string(),
+        ..Default::default()
     };
 
     ledger.process(&withdrawal).expect("Failed to process withdrawal");
@@ -190,32 +201,34 @@ fn dispute_withdrawal() {
     let dispute = Transaction {
         tx_type: DISPUTE.to_string(),
         client: client,
-        tx: tx+1,
+        tx: tid(1),
         amount: "".to_string(),
+        ..Default::default()
     };
 
     ledger.process(&dispute).expect("Failed to dispute withdrawal");
 
     {
         let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
-        assert_eq!(account.available, dec!(50.0));
-        assert_eq!(account.held, dec!(0.0));
-        assert_eq!(account.total, dec!(50.0));
-        assert_eq!(account.locked, true);
+        assert_eq!(account.balance("").available, dec!(50.0));
+        assert_eq!(account.balance("").held, dec!(0.0));
+        assert_eq!(account.balance("").total, dec!(50.0));
+        assert_eq!(account.status, AccountStatus::Frozen);
     }
 }
 
 #[test]
 fn resolve_withdrawal() {
-    let client: u16 = 0;
-    let tx: u32 = 0;
+    let client = cid(0);
+    let tx = tid(0);
     let mut ledger = make_ledger(client, tx, dec!(100.0));
     
     let withdrawal = Transaction {
         tx_type: WITHDRAWAL.to_string(),
         client: client,
-        tx: tx+1,
+        tx: tid(1),
         amount: "50.00".to_string(),
+        ..Default::default()
     };
 
     ledger.process(&withdrawal).expect("Failed to process withdrawal");
@@ -223,8 +236,9 @@ fn resolve_withdrawal() {
     let dispute = Transaction {
         tx_type: DISPUTE.to_string(),
         client: client,
-        tx: tx+1,
+        tx: tid(1),
         amount: "".to_string(),
+        ..Default::default()
     };
 
     ledger.process(&dispute).expect("Failed to dispute withdrawal");
@@ -232,32 +246,34 @@ fn resolve_withdrawal() {
     let resolve = Transaction {
         tx_type: RESOLVE.to_string(),
         client: client,
-        tx: tx+1,
+        tx: tid(1),
         amount: "".to_string(),
+        ..Default::default()
     };
 
     ledger.process(&resolve).expect("Failed to resolve withdrawal");
 
     {
         let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
-        assert_eq!(account.available, dec!(50.0));
-        assert_eq!(account.held, dec!(0.0));
-        assert_eq!(account.total, dec!(50.0));
-        assert_eq!(account.locked, false);
+        assert_eq!(account.balance("").available, dec!(50.0));
+        assert_eq!(account.balance("").held, dec!(0.0));
+        assert_eq!(account.balance("").total, dec!(50.0));
+        assert_eq!(account.status, AccountStatus::Active);
     }
 }
 
 #[test]
 fn chargeback_withdrawal() {
-    let client: u16 = 0;
-    let tx: u32 = 0;
+    let client = cid(0);
+    let tx = tid(0);
     let mut ledger = make_ledger(client, tx, dec!(100.0));
     
     let withdrawal = Transaction {
         tx_type: WITHDRAWAL.to_string(),
         client: client,
-        tx: tx+1,
+        tx: tid(1),
         amount: "50.00".to_string(),
+        ..Default::default()
     };
 
     ledger.process(&withdrawal).expect("Failed to process withdrawal");
@@ -265,8 +281,9 @@ fn chargeback_withdrawal() {
     let dispute = Transaction {
         tx_type: DISPUTE.to_string(),
         client: client,
-        tx: tx+1,
+        tx: tid(1),
         amount: "".to_string(),
+        ..Default::default()
     };
 
     ledger.process(&dispute).expect("Failed to dispute withdrawal");
@@ -274,18 +291,19 @@ fn chargeback_withdrawal() {
     let chargeback = Transaction {
         tx_type: CHARGEBACK.to_string(),
         client: client,
-        tx: tx+1,
+        tx: tid(1),
         amount: "".to_string(),
+        ..Default::default()
     };
 
     ledger.process(&chargeback).expect("Failed to chargeback withdrawal");
 
     {
         let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
-        assert_eq!(account.available, dec!(50.0));
-        assert_eq!(account.held, dec!(0.0));
-        assert_eq!(account.total, dec!(50.0));
-        assert_eq!(account.locked, true);
+        assert_eq!(account.balance("").available, dec!(50.0));
+        assert_eq!(account.balance("").held, dec!(0.0));
+        assert_eq!(account.balance("").total, dec!(50.0));
+        assert_eq!(account.status, AccountStatus::Locked);
     }
 }
 
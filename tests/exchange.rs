@@ -0,0 +1,231 @@
+use rust_decimal_macros::dec;
+use std::fs::File;
+
+use payments::{minor_units, Account, ClientId, Config, Ledger, PaymentError, Rate, RateTable, Transaction, TxId, CLOSE, DEPOSIT, EXCHANGE};
+
+mod helpers;
+
+use helpers::{cid, tid};
+
+const HOUSE: ClientId = cid(99);
+
+fn load_rates() -> RateTable {
+    let file = File::open("tests/fixtures/rates.csv").expect("Failed to open rates fixture");
+
+    RateTable::load(file).expect("Failed to load rates fixture")
+}
+
+// bootstrap a ledger with one client that holds USD
+fn make_fx_ledger(client: ClientId, tx: TxId) -> Ledger {
+    let mut ledger = Ledger::new(Config { rates: load_rates(), house_account: Some(HOUSE), ..Default::default() });
+
+    let deposit = Transaction {
+        tx_type: DEPOSIT.to_string(),
+        client,
+        tx,
+        amount: "100.00".to_string(),
+        currency: "USD".to_string(),
+        ..Default::default()
+    };
+
+    ledger.process(&deposit).expect("Failed to process deposit");
+
+    ledger
+}
+
+fn exchange(client: ClientId, tx: TxId, amount: &str, to: &str, timestamp: Option<u64>) -> Transaction {
+    Transaction {
+        tx_type: EXCHANGE.to_string(),
+        client,
+        tx,
+        amount: amount.to_string(),
+        currency: "USD".to_string(),
+        to_currency: to.to_string(),
+        timestamp,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn quote() {
+    let rates = load_rates();
+
+    let quote = rates.quote("USD", "EUR", dec!(10.00), 0).expect("Failed to quote USD to EUR");
+    assert_eq!(quote.rate, dec!(0.9));
+    assert_eq!(quote.converted, dec!(8.91));
+    assert_eq!(quote.spread, dec!(0.09));
+
+    // later rates replace earlier ones once effective
+    let quote = rates.quote("USD", "EUR", dec!(10.00), 1000).expect("Failed to quote USD to EUR");
+    assert_eq!(quote.rate, dec!(0.8));
+
+    // yen have no minor unit
+    let quote = rates.quote("USD", "JPY", dec!(1.00), 0).expect("Failed to quote USD to JPY");
+    assert_eq!(quote.converted, dec!(149));
+    assert_eq!(quote.spread, dec!(1));
+
+    // a rate without a spread leaves nothing for the house, whatever the rounding
+    let quote = rates.quote("EUR", "USD", dec!(1.05), 0).expect("Failed to quote EUR to USD");
+    assert_eq!(quote.converted, dec!(1.15));
+    assert_eq!(quote.spread, dec!(0));
+
+    assert_eq!(minor_units("JPY"), 0);
+    assert_eq!(minor_units("KWD"), 3);
+    assert_eq!(minor_units("EUR"), 2);
+
+    match rates.quote("EUR", "JPY", dec!(1.00), 0) {
+        Ok(_) => panic!("Quote without rate succeeded"),
+        Err(err) => match err {
+            PaymentError::RateNotFound => (),
+            _ => panic!("Quote without rate failed with wrong error"),
+        }
+    }
+}
+
+#[test]
+fn exchange_with_spread() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_fx_ledger(client, tx);
+
+    ledger.process(&exchange(client, tid(1), "10.00", "EUR", None)).expect("Failed to process exchange");
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert_eq!(account.balance("USD").available, dec!(90.00));
+    assert_eq!(account.balance("USD").total, dec!(90.00));
+    assert_eq!(account.balance("EUR").available, dec!(8.91));
+    assert_eq!(account.balance("EUR").total, dec!(8.91));
+
+    let house: &Account = ledger.accounts.get(&HOUSE).expect("Failed to get house account");
+    assert_eq!(house.balance("EUR").total, dec!(0.09));
+
+    // later txs are priced at the rate in effect at their timestamp
+    ledger.process(&exchange(client, tid(2), "10.00", "EUR", Some(2000))).expect("Failed to process exchange");
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert_eq!(account.balance("EUR").available, dec!(16.83));
+}
+
+#[test]
+fn exchange_rejected() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_fx_ledger(client, tx);
+
+    match ledger.process(&exchange(client, tid(1), "200.00", "EUR", None)) {
+        Ok(_) => panic!("Exchange of more than available succeeded"),
+        Err(err) => match err {
+            PaymentError::InsufficientFunds => (),
+            _ => panic!("Exchange of more than available failed with wrong error"),
+        }
+    }
+
+    match ledger.process(&exchange(client, tid(2), "10.00", "GBP", None)) {
+        Ok(_) => panic!("Exchange without rate succeeded"),
+        Err(err) => match err {
+            PaymentError::RateNotFound => (),
+            _ => panic!("Exchange without rate failed with wrong error"),
+        }
+    }
+
+    match ledger.process(&exchange(client, tid(3), "10.00", "", None)) {
+        Ok(_) => panic!("Exchange without target currency succeeded"),
+        Err(err) => match err {
+            PaymentError::MissingCurrency => (),
+            _ => panic!("Exchange without target currency failed with wrong error"),
+        }
+    }
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert_eq!(account.balance("USD").available, dec!(100.00));
+    assert!(!account.balances.contains_key("GBP"));
+    assert!(!ledger.accounts.contains_key(&HOUSE));
+}
+
+#[test]
+fn house_account() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_fx_ledger(client, tx);
+
+    let close = Transaction {
+        tx_type: CLOSE.to_string(),
+        client: HOUSE,
+        tx: tid(1),
+        admin: true,
+        ..Default::default()
+    };
+
+    ledger.process(&close).expect("Failed to close house account");
+
+    match ledger.process(&exchange(client, tid(2), "10.00", "EUR", None)) {
+        Ok(_) => panic!("Exchange into closed house account succeeded"),
+        Err(err) => match err {
+            PaymentError::HouseAccountClosed => (),
+            _ => panic!("Exchange into closed house account failed with wrong error"),
+        }
+    }
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert_eq!(account.balance("USD").available, dec!(100.00));
+    assert!(!account.balances.contains_key("EUR"));
+}
+
+#[test]
+fn no_house_account() {
+    let rates = RateTable::new(vec![
+        Rate { from: "USD".to_string(), to: "EUR".to_string(), rate: dec!(0.9), spread: dec!(0.01), effective_from: 0 },
+        Rate { from: "USD".to_string(), to: "GBP".to_string(), rate: dec!(0.8), spread: dec!(0), effective_from: 0 },
+    ]);
+    assert!(rates.has_spread());
+
+    let mut ledger = Ledger::new(Config { rates, ..Default::default() });
+    let deposit = Transaction {
+        tx_type: DEPOSIT.to_string(),
+        client: cid(0),
+        tx: tid(0),
+        amount: "100.00".to_string(),
+        currency: "USD".to_string(),
+        ..Default::default()
+    };
+
+    ledger.process(&deposit).expect("Failed to process deposit");
+
+    // the spread would have nowhere to go
+    match ledger.process(&exchange(cid(0), tid(1), "10.00", "EUR", None)) {
+        Ok(_) => panic!("Exchange with spread and no house account succeeded"),
+        Err(err) => match err {
+            PaymentError::HouseAccountMissing => (),
+            _ => panic!("Exchange with spread and no house account failed with wrong error"),
+        }
+    }
+
+    // without a spread there is nothing to credit the house with, even when the converted amount
+    // has to be rounded
+    ledger.process(&exchange(cid(0), tid(2), "10.00", "GBP", None)).expect("Failed to process exchange");
+    ledger.process(&exchange(cid(0), tid(3), "10.01", "GBP", None)).expect("Failed to process rounded exchange");
+
+    let account: &Account = ledger.accounts.get(&cid(0)).expect("Failed to get account for client");
+    assert_eq!(account.balance("USD").available, dec!(79.99));
+    assert_eq!(account.balance("GBP").available, dec!(16.00));
+    assert!(!account.balances.contains_key("EUR"));
+}
+
+#[test]
+fn inexact_amount() {
+    let client = cid(0);
+    let mut ledger = make_fx_ledger(client, tid(0));
+
+    // a fraction of a cent can't be taken from the client
+    match ledger.process(&exchange(client, tid(1), "10.004", "EUR", None)) {
+        Ok(_) => panic!("Exchange of a fraction of a cent succeeded"),
+        Err(err) => match err {
+            PaymentError::InexactAmount => (),
+            _ => panic!("Exchange of a fraction of a cent failed with wrong error"),
+        }
+    }
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert_eq!(account.balance("USD").available, dec!(100.00));
+    assert!(!account.balances.contains_key("EUR"));
+}
@@ -1,19 +1,22 @@
+#![allow(dead_code)]
+
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 
-use payments::{Account, Ledger, Transaction, DEPOSIT, DISPUTE};
+use payments::{Account, ClientId, Ledger, Transaction, TxId, DEPOSIT, DISPUTE};
 
 // bootstrap a ledger with one client that has one deposit tx
-pub fn make_ledger(client: u16, tx: u32, amount: Decimal) -> Ledger {
+pub fn make_ledger(client: ClientId, tx: TxId, amount: Decimal) -> Ledger {
     let mut ledger: Ledger = Default::default();
 
     assert_eq!(ledger.accounts.len(), 0);
     
     let deposit = Transaction {
         tx_type: DEPOSIT.to_string(),
-        client: client,
-        tx: tx,
+        client,
+        tx,
         amount: amount.to_string(),
+        ..Default::default()
     };
 
     ledger.process(&deposit).expect("Failed to process transaction");
@@ -22,21 +25,22 @@ pub fn make_ledger(client: u16, tx: u32, amount: Decimal) -> Ledger {
     
     let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
 
-    assert_eq!(account.available, amount);
-    assert_eq!(account.total, amount);
+    assert_eq!(account.balance("").available, amount);
+    assert_eq!(account.balance("").total, amount);
 
     ledger
 }
 
 // bootstrap a ledger with one client that has one deposit tx
-pub fn make_disputed_ledger(client: u16, tx: u32, amount: Decimal) -> Ledger {
+pub fn make_disputed_ledger(client: ClientId, tx: TxId, amount: Decimal) -> Ledger {
     let mut ledger = make_ledger(client, tx, amount);
 
     let dispute = Transaction {
         tx_type: DISPUTE.to_string(),
-        client: client,
-        tx: tx,
+        client,
+        tx,
         amount: "".to_string(),
+        ..Default::default()
     };
 
     ledger.process(&dispute).expect("Failed to process dispute");
@@ -44,9 +48,31 @@ pub fn make_disputed_ledger(client: u16, tx: u32, amount: Decimal) -> Ledger {
     assert_eq!(ledger.accounts.len(), 1);
 
     let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
-    assert_eq!(account.available, dec!(0.0));
-    assert_eq!(account.held, amount);
-    assert_eq!(account.total, amount);
+    assert_eq!(account.balance("").available, dec!(0.0));
+    assert_eq!(account.balance("").held, amount);
+    assert_eq!(account.balance("").total, amount);
     
     ledger
 }
+
+// the client id numbered n, whatever the id type
+#[cfg(not(feature = "uuid-ids"))]
+pub const fn cid(n: u16) -> ClientId {
+    n as ClientId
+}
+
+// the tx id numbered n, whatever the id type
+#[cfg(not(feature = "uuid-ids"))]
+pub const fn tid(n: u32) -> TxId {
+    n as TxId
+}
+
+#[cfg(feature = "uuid-ids")]
+pub const fn cid(n: u16) -> ClientId {
+    uuid::Uuid::from_u128(n as u128)
+}
+
+#[cfg(feature = "uuid-ids")]
+pub const fn tid(n: u32) -> TxId {
+    uuid::Uuid::from_u128(n as u128)
+}
@@ -0,0 +1,44 @@
+use payments::Transaction;
+
+// a row whose ids don't fit the default u16 client and u32 tx
+const WIDE: &str = "type,client,tx,amount\ndeposit,70000,5000000000,1.0\n";
+
+fn parse(data: &str) -> Result<Transaction, csv::Error> {
+    let mut csv_reader = csv::Reader::from_reader(data.as_bytes());
+
+    csv_reader.deserialize().next().expect("Failed to read row")
+}
+
+#[cfg(not(any(feature = "wide-ids", feature = "uuid-ids")))]
+#[test]
+fn narrow_ids() {
+    assert!(parse(WIDE).is_err());
+}
+
+#[cfg(all(feature = "wide-ids", not(feature = "uuid-ids")))]
+#[test]
+fn wide_ids() {
+    let tx = parse(WIDE).expect("Failed to parse wide ids");
+
+    assert_eq!(tx.client, 70000);
+    assert_eq!(tx.tx, 5000000000);
+}
+
+#[cfg(feature = "uuid-ids")]
+#[test]
+fn uuid_ids() {
+    let data = "type,client,tx,amount\ndeposit,67e55044-10b1-426f-9247-bb680e5fe0c8,9c5b94b1-35ad-49bb-b118-8e8fc24abf80,1.0\n";
+    let tx = parse(data).expect("Failed to parse uuid ids");
+
+    assert_eq!(tx.client.to_string(), "67e55044-10b1-426f-9247-bb680e5fe0c8");
+    assert_eq!(tx.tx.to_string(), "9c5b94b1-35ad-49bb-b118-8e8fc24abf80");
+    assert!(parse(WIDE).is_err());
+}
+
+#[cfg(feature = "uuid-ids")]
+#[test]
+fn uuid_trace_numbers() {
+    let tx = payments::trace_tx_id("091000010000001").expect("Failed to parse trace number");
+
+    assert_eq!(tx, uuid::Uuid::from_u128(91000010000001));
+}
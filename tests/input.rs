@@ -0,0 +1,72 @@
+use rust_decimal_macros::dec;
+
+use payments::{parse_json_line, read_transactions, Account, ClientMap, InputFormat, Ledger, TraceMap, Transaction, DEPOSIT};
+
+mod helpers;
+
+use helpers::{cid, tid};
+
+#[test]
+fn detect() {
+    assert_eq!(InputFormat::detect("txs.csv"), InputFormat::Csv);
+    assert_eq!(InputFormat::detect("txs.jsonl"), InputFormat::JsonLines);
+    assert_eq!(InputFormat::detect("txs.ndjson"), InputFormat::JsonLines);
+    assert_eq!(InputFormat::detect("txs"), InputFormat::Csv);
+}
+
+#[test]
+fn json_amounts() {
+    // numbers, or strings for uuid ids
+    let (client, tx) = (serde_json::json!(cid(1)), serde_json::json!(tid(2)));
+
+    let parsed = parse_json_line(&format!(r#"{{"type":"deposit","client":{},"tx":{},"amount":"1.5"}}"#, client, tx)).expect("Failed to parse string amount");
+    assert_eq!(parsed.tx_type, DEPOSIT);
+    assert_eq!(parsed.client, cid(1));
+    assert_eq!(parsed.tx, tid(2));
+    assert_eq!(parsed.amount, "1.5");
+
+    // digits beyond f64 precision are kept
+    let parsed = parse_json_line(&format!(r#"{{"type":"deposit","client":{},"tx":{},"amount":12345678901234.56789}}"#, client, tx)).expect("Failed to parse number amount");
+    assert_eq!(parsed.amount, "12345678901234.56789");
+
+    let parsed = parse_json_line(&format!(r#"{{"type":"dispute","client":{},"tx":{},"amount":null}}"#, client, tx)).expect("Failed to parse null amount");
+    assert_eq!(parsed.amount, "");
+
+    let parsed = parse_json_line(&format!(r#"{{"type":"dispute","client":{},"tx":{}}}"#, client, tx)).expect("Failed to parse missing amount");
+    assert_eq!(parsed.amount, "");
+
+    assert!(parse_json_line(&format!(r#"{{"type":"deposit","client":"one","tx":{}}}"#, tx)).is_err());
+}
+
+#[test]
+fn read_json_lines() {
+    let (client, first, second) = (serde_json::json!(cid(1)), serde_json::json!(tid(1)), serde_json::json!(tid(2)));
+    let data = format!("{{\"type\":\"deposit\",\"client\":{0},\"tx\":{1},\"amount\":0.1}}\n\
+                        \n\
+                        {{\"type\":\"deposit\",\"client\":{0},\"tx\":{2},\"amount\":\"0.2\"}}\n\
+                        {{\"type\":\"dispute\",\"client\":{0},\"tx\":{1}}}\n", client, first, second);
+
+    let mut ledger: Ledger = Default::default();
+
+    for result in read_transactions(data.as_bytes(), InputFormat::JsonLines, &ClientMap::new(), &TraceMap::new()) {
+        let tx: Transaction = result.expect("Failed to read tx");
+        ledger.process(&tx).expect("Failed to process tx");
+    }
+
+    let account: &Account = ledger.accounts.get(&cid(1)).expect("Failed to get account for client");
+    assert_eq!(account.balance("").available, dec!(0.2));
+    assert_eq!(account.balance("").held, dec!(0.1));
+    assert_eq!(account.balance("").total, dec!(0.3));
+}
+
+#[test]
+fn read_csv() {
+    let data = format!("type,client,tx,amount\ndeposit,{0},{1},0.1\ndeposit,{0},{2},0.2\n", cid(1), tid(1), tid(2));
+
+    let txs: Vec<Transaction> = read_transactions(data.as_bytes(), InputFormat::Csv, &ClientMap::new(), &TraceMap::new())
+        .collect::<Result<_, _>>()
+        .expect("Failed to read txs");
+
+    assert_eq!(txs.len(), 2);
+    assert_eq!(txs[1].amount, "0.2");
+}
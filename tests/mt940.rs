@@ -0,0 +1,130 @@
+// the fixture's entry references are integer tx ids, which aren't uuids
+#![cfg(not(feature = "uuid-ids"))]
+
+use rust_decimal_macros::dec;
+use std::fs::File;
+use std::io::BufReader;
+
+use payments::{import_mt940, read_transactions, ClientMap, Import, InputFormat, Ledger, TraceMap, Transaction, Unmapped, DEPOSIT, WITHDRAWAL};
+
+fn make_client_map() -> ClientMap {
+    let data = "reference,client\nDE00CLIENT0000000001,1\nCLIENT-2,2\nVIRTUAL-3,3\n0532013000,4\n";
+
+    ClientMap::load(data.as_bytes()).expect("Failed to load client map")
+}
+
+fn import() -> Import {
+    let file = File::open("tests/fixtures/mt940.sta").expect("Failed to open statement fixture");
+
+    import_mt940(BufReader::new(file), &make_client_map()).expect("Failed to import statement")
+}
+
+#[test]
+fn entries() {
+    let import = import();
+
+    assert_eq!(import.transactions.len(), 4);
+
+    // SWIFT structured information with the counterparty's IBAN
+    let deposit = &import.transactions[0];
+    assert_eq!(deposit.tx_type, DEPOSIT);
+    assert_eq!(deposit.client, 1);
+    assert_eq!(deposit.tx, 1001);
+    assert_eq!(deposit.amount, "100.50");
+    assert_eq!(deposit.currency, "EUR");
+    assert_eq!(deposit.timestamp, Some(1704153600));
+
+    // SWIFT structured information with the counterparty's account, and a whole amount
+    let withdrawal = &import.transactions[1];
+    assert_eq!(withdrawal.tx_type, WITHDRAWAL);
+    assert_eq!(withdrawal.client, 2);
+    assert_eq!(withdrawal.tx, 1002);
+    assert_eq!(withdrawal.amount, "25");
+    assert!(withdrawal.imported);
+
+    // German structured information wrapped mid subfield, with a funds code and NONREF
+    let structured = &import.transactions[2];
+    assert_eq!(structured.client, 3);
+    assert_eq!(structured.tx, 1003);
+    assert_eq!(structured.amount, "7.25");
+    assert_eq!(structured.currency, "USD");
+
+    // nothing in the information, so the statement's account is used
+    let fallback = &import.transactions[3];
+    assert_eq!(fallback.client, 4);
+    assert_eq!(fallback.tx, 1004);
+}
+
+#[test]
+fn unmapped() {
+    let import = import();
+    let unmapped: Vec<(&str, &str, &str)> = import.unmapped.iter()
+        .map(|u| (u.reference.as_str(), u.account.as_str(), u.reason.as_str()))
+        .collect();
+
+    assert_eq!(unmapped, vec![
+        ("1001", "DE00CLIENT0000000001", "reversal entry"),
+        ("1005", "FR00UNKNOWN000000000", "no client for account"),
+        // a client reference in free text isn't matched
+        ("1006", "DE00HOUSE00000000001", "no client for account"),
+        ("ABC", "0532013000", "entry reference is not a tx id"),
+    ]);
+}
+
+#[test]
+fn read_as_input() {
+    let file = File::open("tests/fixtures/mt940.sta").expect("Failed to open statement fixture");
+    let results: Vec<_> = read_transactions(file, InputFormat::Mt940, &make_client_map(), &TraceMap::new()).collect();
+
+    let unmapped = results.iter().filter(|result| result.as_ref().err().is_some_and(|err| err.is::<Unmapped>())).count();
+    assert_eq!(unmapped, 4);
+
+    let txs: Vec<Transaction> = results.into_iter().filter_map(Result::ok).collect();
+    assert_eq!(txs.iter().map(|tx| tx.tx).collect::<Vec<_>>(), vec![1001, 1002, 1003, 1004]);
+}
+
+#[test]
+fn into_ledger() {
+    let mut ledger: Ledger = Default::default();
+
+    for tx in &import().transactions {
+        if tx.tx_type == DEPOSIT {
+            ledger.process(tx).expect("Failed to process imported tx");
+        }
+    }
+
+    let account = ledger.accounts.get(&3).expect("Failed to get account for client");
+    assert_eq!(account.balance("USD").available, dec!(7.25));
+}
+
+#[test]
+fn bad_lines() {
+    let clients = make_client_map();
+
+    assert!(import_mt940(":61:240102X1,00NTRF1".as_bytes(), &clients).is_err());
+    assert!(import_mt940(":61:2401C".as_bytes(), &clients).is_err());
+
+    // CRLF line endings
+    let import = import_mt940(":25:0532013000\r\n:61:240102C1,00NTRF7\r\n".as_bytes(), &clients).expect("Failed to import statement");
+    assert_eq!(import.transactions[0].client, 4);
+    assert_eq!(import.transactions[0].amount, "1.00");
+}
+
+#[test]
+fn counterparty_name() {
+    let mut clients = make_client_map();
+    clients.insert("Max Mustermann", 5);
+
+    // only the counterparty's account in ?31 is matched, not its name in ?32
+    let import = import_mt940(":25:DE00HOUSE00000000001\n:61:240102C1,00NTRF7\n:86:166?20SVWZ+Refund?31FR00UNKNOWN000000000?32Max Mustermann\n".as_bytes(), &clients).expect("Failed to import statement");
+
+    assert!(import.transactions.is_empty());
+    assert_eq!(import.unmapped[0].account, "FR00UNKNOWN000000000");
+    assert_eq!(import.unmapped[0].reason, "no client for account");
+}
+
+#[test]
+fn detect() {
+    assert_eq!(InputFormat::detect("statement.sta"), InputFormat::Mt940);
+    assert_eq!(InputFormat::detect("statement.mt940.gz"), InputFormat::Mt940);
+}
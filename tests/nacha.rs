@@ -0,0 +1,195 @@
+use rust_decimal_macros::dec;
+use std::fs::File;
+use std::io::BufReader;
+
+use payments::{import_nacha, read_transactions, trace_source, trace_tx_id, write_nacha_payouts, AccountStatus, ClientId, ClientMap, Config, IdScope, Import, InputFormat, Ledger, Originator, PayeeDirectory, PaymentError, Settlement, TraceMap, Transaction, TxId, Unmapped, DEPOSIT, RETURNED, WITHDRAWAL};
+
+mod helpers;
+
+use helpers::{cid, tid};
+
+fn make_client_map() -> ClientMap {
+    let data = format!("reference,client\n11111111,{}\n22222222,{}\nCUST3,{}\n", cid(1), cid(2), cid(3));
+
+    ClientMap::load(data.as_bytes()).expect("Failed to load client map")
+}
+
+fn import() -> Import {
+    let file = File::open("tests/fixtures/nacha.ach").expect("Failed to open NACHA fixture");
+
+    import_nacha(BufReader::new(file), &make_client_map(), &TraceMap::new()).expect("Failed to import NACHA file")
+}
+
+// the tx id of the fixture's trace number with the passed sequence number
+fn trace(sequence: u32) -> TxId {
+    trace_tx_id(&format!("09100001{:07}", sequence)).expect("Bad trace number")
+}
+
+// a ledger whose tx ids are unique within each source, as trace numbers need without wide ids
+fn make_nacha_ledger(settlement: Settlement) -> Ledger {
+    Ledger::new(Config { settlement, id_scope: IdScope::Source, ..Default::default() })
+}
+
+#[test]
+fn entries() {
+    let import = import();
+    let txs: Vec<(&str, ClientId, TxId, &str)> = import.transactions.iter()
+        .map(|tx| (tx.tx_type.as_str(), tx.client, tx.tx, tx.amount.as_str()))
+        .collect();
+
+    assert_eq!(txs, vec![
+        (DEPOSIT, cid(1), trace(1), "100.50"),
+        (WITHDRAWAL, cid(2), trace(2), "25.25"),
+        (DEPOSIT, cid(3), trace(5), "7.00"),
+        (RETURNED, cid(1), trace(1), ""),
+    ]);
+
+    // every tx is from the originating bank
+    assert!(import.transactions.iter().all(|tx| tx.source.as_deref() == Some("09100001")));
+
+    // effective entry date of the batch
+    assert_eq!(import.transactions[0].timestamp, Some(1704153600));
+    assert_eq!(import.transactions[3].reason, "R10");
+}
+
+#[test]
+fn unmapped() {
+    let import = import();
+    let unmapped: Vec<(&str, &str)> = import.unmapped.iter()
+        .map(|u| (u.reference.as_str(), u.reason.as_str()))
+        .collect();
+
+    assert_eq!(unmapped, vec![
+        ("091000010000003", "prenote"),
+        ("091000010000004", "no client for account"),
+        ("091000010000007", "notification of change"),
+    ]);
+}
+
+#[test]
+fn read_as_input() {
+    let file = File::open("tests/fixtures/nacha.ach").expect("Failed to open NACHA fixture");
+    let results: Vec<_> = read_transactions(file, InputFormat::Nacha, &make_client_map(), &TraceMap::new()).collect();
+
+    // unmapped entries are errors which can be skipped
+    let unmapped: Vec<&str> = results.iter()
+        .filter_map(|result| result.as_ref().err().and_then(|err| err.downcast_ref::<Unmapped>()))
+        .map(|entry| entry.reason.as_str())
+        .collect();
+    assert_eq!(unmapped, vec!["prenote", "no client for account", "notification of change"]);
+
+    let txs: Vec<Transaction> = results.into_iter().filter_map(Result::ok).collect();
+    assert_eq!(txs.len(), 4);
+}
+
+#[test]
+fn return_charges_back() {
+    let mut ledger = make_nacha_ledger(Settlement::Immediate);
+
+    for tx in &import().transactions {
+        if tx.tx_type != WITHDRAWAL {
+            ledger.process(tx).expect("Failed to process imported tx");
+        }
+    }
+
+    let account = ledger.accounts.get(&cid(1)).expect("Failed to get account for client");
+    assert_eq!(account.balance("").total, dec!(0.00));
+    assert_eq!(account.status, AccountStatus::Locked);
+}
+
+#[test]
+fn return_of_pending_deposit() {
+    let mut ledger = make_nacha_ledger(Settlement::Explicit);
+
+    for tx in &import().transactions {
+        if tx.tx_type != WITHDRAWAL {
+            ledger.process(tx).expect("Failed to process imported tx");
+        }
+    }
+
+    // the unsettled deposit is just returned, the account isn't locked
+    let account = ledger.accounts.get(&cid(1)).expect("Failed to get account for client");
+    assert_eq!(account.balance("").pending, dec!(0.00));
+    assert_eq!(account.balance("").total, dec!(0.00));
+    assert_eq!(account.status, AccountStatus::Active);
+}
+
+#[test]
+fn returned_payout() {
+    let mut ledger = make_nacha_ledger(Settlement::Immediate);
+
+    for (tx_type, tx, amount) in &[(DEPOSIT, tid(1), "10.00"), (WITHDRAWAL, tid(2), "4.50")] {
+        let transaction = Transaction {
+            tx_type: tx_type.to_string(),
+            client: cid(1),
+            tx: *tx,
+            amount: amount.to_string(),
+            ..Default::default()
+        };
+
+        ledger.process(&transaction).expect("Failed to process tx");
+    }
+
+    let payees = PayeeDirectory::load(format!("client,name,account,bank\n{},Alice,99999999,021000021\n", cid(1)).as_bytes()).expect("Failed to load payees");
+    let originator = Originator { name: "Payments Inc".to_string(), bank: "091000019".to_string(), currency: "USD".to_string(), ..Default::default() };
+    let mut traces = TraceMap::new();
+
+    write_nacha_payouts(Vec::new(), &ledger.payouts, ledger.payout_total(), &payees, &originator, 0, &mut traces).expect("Failed to write payouts");
+
+    // the receiving bank returns the credit, naming the payout's trace number, to an account
+    // which isn't in the client map
+    let entry = format!("621021000021{:17}0000000450{:15}{:22}  1021000020000009", "99999999", "", "ALICE");
+    let addenda = format!("799R03091000010000001{:58}021000020000009", "");
+    let import = import_nacha(format!("{}\n{}\n", entry, addenda).as_bytes(), &make_client_map(), &traces).expect("Failed to import return");
+
+    assert!(import.unmapped.is_empty());
+    assert_eq!(import.transactions.len(), 1);
+    assert_eq!((import.transactions[0].tx_type.as_str(), import.transactions[0].client, import.transactions[0].tx), (RETURNED, cid(1), tid(2)));
+
+    // the withdrawal's funds go back to the client, once
+    ledger.process(&import.transactions[0]).expect("Failed to process returned payout");
+
+    match ledger.process(&import.transactions[0]) {
+        Ok(_) => panic!("Second return of a payout succeeded"),
+        Err(err) => match err {
+            PaymentError::AlreadyReturned => (),
+            _ => panic!("Second return of a payout failed with wrong error"),
+        }
+    }
+
+    let account = ledger.accounts.get(&cid(1)).expect("Failed to get account for client");
+    assert_eq!(account.balance("").available, dec!(10.00));
+    assert_eq!(account.balance("").total, dec!(10.00));
+    assert_eq!(account.status, AccountStatus::Active);
+}
+
+#[test]
+fn bad_records() {
+    let clients = make_client_map();
+    let traces = TraceMap::new();
+
+    assert!(import_nacha("6220910000191111".as_bytes(), &clients, &traces).is_err());
+    assert!(import_nacha(format!("799{}", " ".repeat(91)).as_bytes(), &clients, &traces).is_err());
+
+    // 94 bytes, but not 94 characters
+    assert!(import_nacha(format!("é{}", " ".repeat(92)).as_bytes(), &clients, &traces).is_err());
+}
+
+#[test]
+fn trace_numbers() {
+    // the same sequence number from two banks
+    let first = trace_tx_id("091000010000123");
+    let second = trace_tx_id("021000020000123");
+
+    assert!(first.is_ok());
+    assert_ne!(trace_source("091000010000123"), trace_source("021000020000123"));
+
+    // without wide ids only the sources tell them apart
+    #[cfg(not(any(feature = "wide-ids", feature = "uuid-ids")))]
+    assert_eq!(second, Ok(tid(123)));
+    #[cfg(any(feature = "wide-ids", feature = "uuid-ids"))]
+    assert_ne!(first, second);
+
+    assert_eq!(trace_tx_id("123"), Err("bad trace number"));
+    assert_eq!(trace_tx_id("09100001000012X"), Err("bad trace number"));
+}
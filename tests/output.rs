@@ -0,0 +1,84 @@
+use rust_decimal_macros::dec;
+
+use payments::{Account, AccountRecord, AccountStatus, OutputFormat};
+
+mod helpers;
+
+use helpers::cid;
+
+// write the passed accounts in the passed format
+fn write(accounts: &[Account], format: OutputFormat) -> String {
+    let mut output = vec![];
+
+    {
+        let mut writer = format.writer(&mut output);
+
+        for account in accounts {
+            for record in AccountRecord::all(account, false) {
+                writer.write(&record).expect("Failed to write account");
+            }
+        }
+
+        writer.finish().expect("Failed to finish output");
+    }
+
+    String::from_utf8(output).expect("Bad utf8")
+}
+
+fn make_accounts() -> Vec<Account> {
+    let mut account = Account::new(cid(1));
+    account.balance_mut("").available = dec!(1.2345);
+    account.balance_mut("").total = dec!(1.2345);
+
+    vec![account, Account::new(cid(2))]
+}
+
+#[test]
+fn csv() {
+    assert_eq!(write(&make_accounts(), OutputFormat::Csv),
+               format!("client,currency,available,held,pending,total,locked,status,reason,admin\n\
+                        {},,1.2345,0.0,0.0,1.2345,false,active,,false\n\
+                        {},,0.0,0.0,0.0,0.0,false,active,,false\n", cid(1), cid(2)));
+}
+
+#[test]
+fn json() {
+    // numbers, or strings for uuid ids
+    let (one, two) = (serde_json::json!(cid(1)), serde_json::json!(cid(2)));
+
+    assert_eq!(write(&make_accounts(), OutputFormat::Json),
+               format!("[\n  \
+                        {{\"client\":{},\"currency\":\"\",\"available\":\"1.2345\",\"held\":\"0.0\",\"pending\":\"0.0\",\"total\":\"1.2345\",\"locked\":false,\"status\":\"active\",\"reason\":\"\",\"admin\":false}},\n  \
+                        {{\"client\":{},\"currency\":\"\",\"available\":\"0.0\",\"held\":\"0.0\",\"pending\":\"0.0\",\"total\":\"0.0\",\"locked\":false,\"status\":\"active\",\"reason\":\"\",\"admin\":false}}\n\
+                        ]\n", one, two));
+
+    assert_eq!(write(&[], OutputFormat::Json), "[]\n");
+}
+
+#[test]
+fn json_lines() {
+    let output = write(&make_accounts(), OutputFormat::JsonLines);
+    let lines: Vec<&str> = output.lines().collect();
+
+    assert_eq!(lines.len(), 2);
+
+    let value: serde_json::Value = serde_json::from_str(lines[0]).expect("Failed to parse json line");
+    assert_eq!(value["client"], serde_json::json!(cid(1)));
+    assert_eq!(value["available"], "1.2345");
+    assert_eq!(value["status"], "active");
+}
+
+#[test]
+fn locked_column() {
+    let mut accounts = vec![Account::new(cid(1)), Account::new(cid(2)), Account::new(cid(3))];
+    accounts[0].status = AccountStatus::UnderReview;
+    accounts[1].status = AccountStatus::Frozen;
+    accounts[2].status = AccountStatus::Locked;
+
+    // frozen accounts reject deposits and withdrawals, so were locked before there were statuses
+    assert_eq!(write(&accounts, OutputFormat::Csv),
+               format!("client,currency,available,held,pending,total,locked,status,reason,admin\n\
+                        {},,0.0,0.0,0.0,0.0,false,under_review,,false\n\
+                        {},,0.0,0.0,0.0,0.0,true,frozen,,false\n\
+                        {},,0.0,0.0,0.0,0.0,true,locked,,false\n", cid(1), cid(2), cid(3)));
+}
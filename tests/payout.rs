@@ -0,0 +1,268 @@
+use rust_decimal_macros::dec;
+
+use payments::{format_date, parse_date, valid_routing, write_nacha_payouts, write_pain001_payouts, Ledger, Originator, PayeeDirectory, PaymentError, Payout, PayoutTrace, TraceMap, Transaction, DEPOSIT, RETURNED, WITHDRAWAL};
+
+mod helpers;
+
+use helpers::{cid, tid};
+
+// 2024-01-02 12:30
+const DATE: u64 = 1704198600;
+
+fn make_payees() -> PayeeDirectory {
+    let data = format!("client,name,account,bank\n{},Alice Example,11111111,021000021\n{},Bob Example,DE89370400440532013000,COBADEFFXXX\n", cid(1), cid(2));
+
+    PayeeDirectory::load(data.as_bytes()).expect("Failed to load payees")
+}
+
+fn make_originator() -> Originator {
+    let data = r#"{"name": "Example Payments", "id": "1234567890", "account": "GB29NWBK60161331926819", "bank": "091000019", "currency": "USD"}"#;
+
+    Originator::load(data.as_bytes()).expect("Failed to load originator")
+}
+
+fn make_payout(client: u16, tx: u32, amount: &str) -> Payout {
+    Payout {
+        client: cid(client),
+        tx: tid(tx),
+        amount: amount.parse().expect("Failed to parse amount"),
+        currency: String::new(),
+        timestamp: None,
+        source: None,
+    }
+}
+
+fn make_tx(tx_type: &str, client: u16, tx: u32, amount: &str) -> Transaction {
+    Transaction {
+        tx_type: tx_type.to_string(),
+        client: cid(client),
+        tx: tid(tx),
+        amount: amount.to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn ledger_payouts() {
+    let mut ledger: Ledger = Default::default();
+
+    ledger.process(&make_tx(DEPOSIT, 1, 1, "10.0")).expect("Failed to process deposit");
+    ledger.process(&make_tx(WITHDRAWAL, 1, 2, "4.5")).expect("Failed to process withdrawal");
+    assert!(ledger.process(&make_tx(WITHDRAWAL, 1, 3, "6.0")).is_err());
+
+    assert_eq!(ledger.payouts, vec![make_payout(1, 2, "4.5")]);
+
+    // a debit on a bank statement has already been paid
+    let imported = Transaction {
+        imported: true,
+        ..make_tx(WITHDRAWAL, 1, 4, "1.0")
+    };
+
+    ledger.process(&imported).expect("Failed to process imported withdrawal");
+    assert_eq!(ledger.payouts, vec![make_payout(1, 2, "4.5")]);
+    assert_eq!(ledger.payout_total(), dec!(4.5));
+}
+
+#[test]
+fn nacha() {
+    let payouts = vec![make_payout(1, 2, "4.5"), make_payout(1, 7, "100.25")];
+    let mut traces = TraceMap::new();
+    let mut data = Vec::new();
+
+    write_nacha_payouts(&mut data, &payouts, dec!(104.75), &make_payees(), &make_originator(), DATE, &mut traces).expect("Failed to write payouts");
+
+    let text = String::from_utf8(data).expect("Failed to read payouts");
+    let records: Vec<&str> = text.lines().collect();
+
+    assert_eq!(records.len(), 10);
+    assert!(records.iter().all(|record| record.len() == 94));
+    assert!(records[0].starts_with("101 091000019123456789024010212"));
+
+    // entry count, entry hash of the RDFI routing numbers, debits and credits
+    assert_eq!(&records[4][..44], "82200000020004200004000000000000000000010475");
+    assert_eq!(&records[5][..55], "9000001000001000000020004200004000000000000000000010475");
+
+    // trace numbers are the originator's routing number and a sequence number
+    assert!(records[2].ends_with("091000010000001"));
+    assert!(records[3].ends_with("091000010000002"));
+
+    assert_eq!(traces.get("091000010000002"), Some(&PayoutTrace { trace: "091000010000002".to_string(), client: cid(1), tx: tid(7), source: None }));
+
+    // the next file carries on from the last sequence number
+    let mut data = Vec::new();
+    write_nacha_payouts(&mut data, &payouts[..1], dec!(4.5), &make_payees(), &make_originator(), DATE, &mut traces).expect("Failed to write payouts");
+
+    let text = String::from_utf8(data).expect("Failed to read payouts");
+    assert!(text.lines().nth(2).expect("No entry").ends_with("091000010000003"));
+    assert_eq!(traces.next_sequence("09100001"), 4);
+
+    let mut csv = Vec::new();
+    traces.write(&mut csv).expect("Failed to write traces");
+    assert_eq!(TraceMap::load(csv.as_slice()).expect("Failed to load traces"), traces);
+}
+
+#[test]
+fn pain001() {
+    let mut euro = make_payout(2, 3, "20");
+    euro.currency = "EUR".to_string();
+
+    let payouts = vec![make_payout(1, 2, "4.5"), euro];
+    let mut data = Vec::new();
+
+    write_pain001_payouts(&mut data, &payouts, dec!(24.5), &make_payees(), &make_originator(), DATE).expect("Failed to write payouts");
+
+    let text = String::from_utf8(data).expect("Failed to read payouts");
+
+    assert!(text.contains("<MsgId>PAYOUT-1704198600</MsgId>"));
+    assert!(text.contains("<CreDtTm>2024-01-02T12:30:00</CreDtTm>"));
+    assert!(text.contains("<NbOfTxs>2</NbOfTxs>"));
+    assert!(text.contains("<CtrlSum>24.50</CtrlSum>"));
+    assert!(text.contains("<InstdAmt Ccy=\"USD\">4.50</InstdAmt>"));
+    assert!(text.contains("<InstdAmt Ccy=\"EUR\">20.00</InstdAmt>"));
+    assert!(text.contains("<IBAN>DE89370400440532013000</IBAN>"));
+    assert!(text.contains("<BIC>COBADEFFXXX</BIC>"));
+    assert!(text.contains("<Id>11111111</Id>"));
+    assert_eq!(text.matches("<PmtInf>").count(), 2);
+}
+
+#[test]
+fn non_ascii_accounts() {
+    let payees = PayeeDirectory::load(format!("client,name,account,bank\n{},Zoë Example,Zé123456,COBADEFFXXX\n", cid(1)).as_bytes()).expect("Failed to load payees");
+    let mut euro = make_payout(1, 2, "5");
+    euro.currency = "EUR".to_string();
+
+    let mut data = Vec::new();
+    write_pain001_payouts(&mut data, &[euro], dec!(5), &payees, &make_originator(), DATE).expect("Failed to write payouts");
+
+    let text = String::from_utf8(data).expect("Failed to read payouts");
+    assert!(text.contains("<Id>Zé123456</Id>"));
+    assert!(!text.contains("<IBAN>Zé123456</IBAN>"));
+}
+
+#[test]
+fn unpayable() {
+    let payees = make_payees();
+    let originator = make_originator();
+    let write = |payouts: &[Payout]| {
+        let mut data = Vec::new();
+        let mut traces = TraceMap::new();
+        let total = payouts.iter().map(|payout| payout.amount).sum();
+        let result = write_nacha_payouts(&mut data, payouts, total, &payees, &originator, DATE, &mut traces);
+        assert_eq!(traces.next_sequence("09100001"), 1);
+        (result.is_ok(), data.is_empty())
+    };
+
+    // nothing is written unless every payout can be
+    assert_eq!(write(&[make_payout(1, 1, "1.00"), make_payout(3, 2, "1.00")]), (false, true));
+    assert_eq!(write(&[make_payout(1, 1, "1.005")]), (false, true));
+    assert_eq!(write(&[make_payout(2, 1, "1.00")]), (false, true));
+
+    let mut euro = make_payout(1, 1, "1.00");
+    euro.currency = "EUR".to_string();
+    assert_eq!(write(&[euro]), (false, true));
+
+    let mut data = Vec::new();
+    assert!(write_pain001_payouts(&mut data, &[make_payout(1, 1, "1.001")], dec!(1.001), &payees, &originator, DATE).is_err());
+}
+
+#[test]
+fn control_totals() {
+    let payees = make_payees();
+    let originator = make_originator();
+
+    // a payout which went missing after its withdrawal was accepted
+    let payouts = vec![make_payout(1, 2, "4.5")];
+
+    let mut data = Vec::new();
+    assert!(write_nacha_payouts(&mut data, &payouts, dec!(104.75), &payees, &originator, DATE, &mut TraceMap::new()).is_err());
+    assert!(data.is_empty());
+
+    assert!(write_pain001_payouts(&mut data, &payouts, dec!(104.75), &payees, &originator, DATE).is_err());
+    assert!(data.is_empty());
+}
+
+#[test]
+fn changed_payouts() {
+    let mut ledger: Ledger = Default::default();
+
+    ledger.process(&make_tx(DEPOSIT, 1, 1, "10.0")).expect("Failed to process deposit");
+    ledger.process(&make_tx(WITHDRAWAL, 1, 2, "2.0")).expect("Failed to process withdrawal");
+    ledger.process(&make_tx(WITHDRAWAL, 1, 3, "3.0")).expect("Failed to process withdrawal");
+    assert_eq!(ledger.payout_total(), dec!(5.0));
+
+    let payees = make_payees();
+    let originator = make_originator();
+    let write = |payouts: &[Payout], total| {
+        let mut nacha = Vec::new();
+        let mut pain001 = Vec::new();
+        let nacha_result = write_nacha_payouts(&mut nacha, payouts, total, &payees, &originator, DATE, &mut TraceMap::new());
+        let pain001_result = write_pain001_payouts(&mut pain001, payouts, total, &payees, &originator, DATE);
+        (nacha_result.is_ok(), pain001_result.is_ok(), nacha.is_empty() && pain001.is_empty())
+    };
+
+    assert_eq!(write(&ledger.payouts, ledger.payout_total()), (true, true, false));
+
+    // the total comes from the accepted withdrawals, so a payout lost or changed since doesn't match
+    let mut changed = ledger.payouts.clone();
+    changed[1].amount = dec!(30.0);
+    assert_eq!(write(&changed, ledger.payout_total()), (false, false, true));
+
+    ledger.payouts.remove(0);
+    assert_eq!(write(&ledger.payouts, ledger.payout_total()), (false, false, true));
+}
+
+#[test]
+fn returned_payouts() {
+    let mut ledger: Ledger = Default::default();
+
+    ledger.process(&make_tx(DEPOSIT, 1, 1, "100.0")).expect("Failed to process deposit");
+    ledger.process(&make_tx(WITHDRAWAL, 1, 2, "40.0")).expect("Failed to process withdrawal");
+    ledger.process(&make_tx(WITHDRAWAL, 1, 3, "10.0")).expect("Failed to process withdrawal");
+
+    // only a bank's return file can return a withdrawal
+    match ledger.process(&make_tx(RETURNED, 1, 2, "")) {
+        Ok(_) => panic!("Return of a withdrawal from the input succeeded"),
+        Err(err) => match err {
+            PaymentError::ReturnNotImported => (),
+            _ => panic!("Return of a withdrawal from the input failed with wrong error"),
+        }
+    }
+
+    assert_eq!(ledger.payouts.len(), 2);
+
+    // the bank couldn't pay the first withdrawal out, so its funds are back with the client
+    let returned = Transaction {
+        imported: true,
+        ..make_tx(RETURNED, 1, 2, "")
+    };
+
+    ledger.process(&returned).expect("Failed to process returned withdrawal");
+
+    let account = ledger.accounts.get(&cid(1)).expect("Failed to get account for client");
+    assert_eq!(account.balance("").available, dec!(90.0));
+
+    // and it isn't paid out again
+    assert_eq!(ledger.payouts, vec![make_payout(1, 3, "10.0")]);
+    assert_eq!(ledger.payout_total(), dec!(10.0));
+
+    let mut data = Vec::new();
+    write_pain001_payouts(&mut data, &ledger.payouts, ledger.payout_total(), &make_payees(), &make_originator(), DATE).expect("Failed to write payouts");
+
+    let text = String::from_utf8(data).expect("Failed to read payouts");
+    assert!(text.contains("<CtrlSum>10.00</CtrlSum>"));
+    assert!(!text.contains("40.00"));
+}
+
+#[test]
+fn routing_numbers() {
+    assert!(valid_routing("091000019"));
+    assert!(!valid_routing("091000018"));
+    assert!(!valid_routing("09100001"));
+}
+
+#[test]
+fn dates() {
+    assert_eq!(format_date(DATE), "2024-01-02");
+    assert_eq!(format_date(951782400), "2000-02-29");
+    assert_eq!(parse_date(&format_date(DATE)), Some(1704153600));
+}
@@ -0,0 +1,117 @@
+use rust_decimal_macros::dec;
+
+use payments::{Account, AccountRecord, LockEvent, Transaction, CHARGEBACK, DEPOSIT, DISPUTE, UNLOCK, WITHDRAWAL};
+
+mod helpers;
+
+use helpers::{cid, tid, make_disputed_ledger};
+
+#[test]
+fn lock_events() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_disputed_ledger(client, tx, dec!(100.0));
+
+    let chargeback = Transaction {
+        tx_type: CHARGEBACK.to_string(),
+        client,
+        tx,
+        ..Default::default()
+    };
+
+    ledger.process(&chargeback).expect("Failed to process chargeback");
+
+    let withdrawal = Transaction {
+        tx_type: WITHDRAWAL.to_string(),
+        client,
+        tx: tid(1),
+        amount: "10.00".to_string(),
+        ..Default::default()
+    };
+
+    ledger.process(&withdrawal).expect_err("Withdrawal from locked account succeeded");
+
+    // rejected txs leave no lock event behind
+    let dispute = Transaction {
+        tx_type: DISPUTE.to_string(),
+        client,
+        tx: tid(1),
+        ..Default::default()
+    };
+
+    ledger.process(&dispute).expect_err("Dispute of rejected withdrawal succeeded");
+
+    {
+        let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+        assert_eq!(account.locked_by(), Some(&LockEvent { tx, kind: CHARGEBACK.to_string(), ordinal: 3 }));
+        assert_eq!(account.lock_events.len(), 1);
+
+        let mut writer = csv::Writer::from_writer(vec![]);
+        writer.serialize(AccountRecord::new(account, "", true)).expect("Failed to serialize account");
+        let output = String::from_utf8(writer.into_inner().expect("Failed to flush csv")).expect("Bad utf8");
+        assert_eq!(output, format!("client,currency,available,held,pending,total,locked,status,reason,admin,lock_reason,locked_by_tx\n\
+                                    {},,0.0,0.0,0.0,0.0,true,locked,chargeback of tx {},false,chargeback,{}\n", client, tx, tx));
+    }
+
+    let unlock = Transaction {
+        tx_type: UNLOCK.to_string(),
+        client,
+        tx: tid(2),
+        admin: true,
+        ..Default::default()
+    };
+
+    ledger.process(&unlock).expect("Failed to process unlock");
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert_eq!(account.locked_by(), None);
+
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.serialize(AccountRecord::new(account, "", false)).expect("Failed to serialize account");
+    let output = String::from_utf8(writer.into_inner().expect("Failed to flush csv")).expect("Bad utf8");
+    assert_eq!(output, format!("client,currency,available,held,pending,total,locked,status,reason,admin\n{},,0.0,0.0,0.0,0.0,false,active,,true\n", client));
+}
+
+#[test]
+fn subsequent_lock_events() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_disputed_ledger(client, tx, dec!(100.0));
+
+    let deposit = Transaction {
+        tx_type: DEPOSIT.to_string(),
+        client,
+        tx: tid(2),
+        amount: "1.00".to_string(),
+        ..Default::default()
+    };
+
+    ledger.process(&deposit).expect("Failed to process deposit");
+
+    let withdrawal = Transaction {
+        tx_type: WITHDRAWAL.to_string(),
+        client,
+        tx: tid(1),
+        amount: "1.00".to_string(),
+        ..Default::default()
+    };
+
+    ledger.process(&withdrawal).expect("Failed to process withdrawal");
+
+    for (tx_type, id) in &[(DISPUTE, tid(1)), (CHARGEBACK, tx)] {
+        let dispute = Transaction {
+            tx_type: tx_type.to_string(),
+            client,
+            tx: *id,
+            ..Default::default()
+        };
+
+        ledger.process(&dispute).expect("Failed to process dispute");
+    }
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert_eq!(account.lock_events, vec![
+        LockEvent { tx: tid(1), kind: DISPUTE.to_string(), ordinal: 5 },
+        LockEvent { tx, kind: CHARGEBACK.to_string(), ordinal: 6 },
+    ]);
+}
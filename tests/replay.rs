@@ -0,0 +1,80 @@
+use rust_decimal_macros::dec;
+
+use payments::{Account, Outcome, PaymentError, Transaction, DEPOSIT, WITHDRAWAL};
+
+mod helpers;
+
+use helpers::{cid, tid, make_ledger};
+
+#[test]
+fn replayed_deposit() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_ledger(client, tx, dec!(100.0));
+
+    // same payment, amount written differently
+    let deposit = Transaction {
+        tx_type: DEPOSIT.to_string(),
+        client,
+        tx,
+        amount: "100.000".to_string(),
+        ..Default::default()
+    };
+
+    assert_eq!(ledger.process(&deposit).expect("Failed to replay deposit"), Outcome::AlreadyApplied);
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert_eq!(account.balance("").available, dec!(100.0));
+    assert_eq!(account.balance("").total, dec!(100.0));
+}
+
+#[test]
+fn replayed_withdrawal() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_ledger(client, tx, dec!(100.0));
+
+    let withdrawal = Transaction {
+        tx_type: WITHDRAWAL.to_string(),
+        client,
+        tx: tid(1),
+        amount: "40.00".to_string(),
+        ..Default::default()
+    };
+
+    assert_eq!(ledger.process(&withdrawal).expect("Failed to process withdrawal"), Outcome::Applied);
+    assert_eq!(ledger.process(&withdrawal).expect("Failed to replay withdrawal"), Outcome::AlreadyApplied);
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert_eq!(account.balance("").available, dec!(60.0));
+}
+
+#[test]
+fn conflicting_duplicate() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_ledger(client, tx, dec!(100.0));
+
+    let deposit = Transaction {
+        tx_type: DEPOSIT.to_string(),
+        client: cid(1),
+        tx,
+        amount: "100.00".to_string(),
+        ..Default::default()
+    };
+
+    match ledger.process(&deposit) {
+        Ok(_) => panic!("Conflicting duplicate succeeded"),
+        Err(err) => match err {
+            PaymentError::DuplicateTransaction { stored, incoming } => {
+                assert_eq!(stored.client, client);
+                assert_eq!(stored.amount, "100.0");
+                assert_eq!(incoming.client, cid(1));
+                assert_eq!(incoming.amount, "100.00");
+            },
+            _ => panic!("Conflicting duplicate failed with wrong error"),
+        }
+    }
+
+    assert_eq!(ledger.accounts.get(&cid(1)).expect("Failed to get account for client").balance("").total, dec!(0.0));
+}
@@ -0,0 +1,186 @@
+use rust_decimal_macros::dec;
+
+use payments::{Account, AccountStatus, Config, Ledger, Outcome, PaymentError, RiskEvent, RiskRule, Threshold, Transaction, CHARGEBACK, DEPOSIT, DISPUTE, WITHDRAWAL};
+
+mod helpers;
+
+use helpers::{cid, tid};
+
+// bootstrap a ledger with the passed risk rules and one client that has three deposits
+fn make_risk_ledger(risk: Vec<RiskRule>) -> Ledger {
+    let config = Config {
+        risk,
+        ..Default::default()
+    };
+    let mut ledger = Ledger::new(config);
+
+    for tx in 0..3 {
+        let deposit = Transaction {
+            tx_type: DEPOSIT.to_string(),
+            client: cid(0),
+            tx: tid(tx),
+            amount: "100.00".to_string(),
+            ..Default::default()
+        };
+
+        ledger.process(&deposit).expect("Failed to process deposit");
+    }
+
+    ledger
+}
+
+// process a dispute or chargeback of the passed deposit
+fn dispute(ledger: &mut Ledger, tx_type: &str, tx: u32) -> Result<Outcome, PaymentError> {
+    let dispute = Transaction {
+        tx_type: tx_type.to_string(),
+        client: cid(0),
+        tx: tid(tx),
+        ..Default::default()
+    };
+
+    ledger.process(&dispute)
+}
+
+#[test]
+fn open_disputes() {
+    let risk = vec![RiskRule { name: "disputes".to_string(), threshold: Threshold::OpenDisputes(1), status: AccountStatus::UnderReview, currency: None }];
+    let mut ledger = make_risk_ledger(risk);
+
+    dispute(&mut ledger, DISPUTE, 0).expect("Failed to process dispute");
+    assert_eq!(ledger.accounts.get(&cid(0)).expect("Failed to get account for client").status, AccountStatus::Active);
+
+    dispute(&mut ledger, DISPUTE, 1).expect("Failed to process dispute");
+
+    assert_eq!(ledger.risk_events, vec![RiskEvent {
+        client: cid(0),
+        tx: tid(1),
+        rule: "disputes".to_string(),
+        currency: String::new(),
+        status: AccountStatus::UnderReview,
+        detail: "2 open disputes exceed 1".to_string(),
+    }]);
+
+    // the rule doesn't fire again while the account is under review
+    dispute(&mut ledger, DISPUTE, 2).expect("Failed to process dispute");
+    assert_eq!(ledger.risk_events.len(), 1);
+
+    let withdrawal = Transaction {
+        tx_type: WITHDRAWAL.to_string(),
+        client: cid(0),
+        tx: tid(3),
+        amount: "1.00".to_string(),
+        ..Default::default()
+    };
+
+    match ledger.process(&withdrawal) {
+        Ok(_) => panic!("Withdrawal from account under review succeeded"),
+        Err(err) => match err {
+            PaymentError::AccountUnderReview => (),
+            _ => panic!("Withdrawal from account under review failed with wrong error"),
+        }
+    }
+
+    let account: &Account = ledger.accounts.get(&cid(0)).expect("Failed to get account for client");
+    assert_eq!(account.reason, "risk rule disputes: 2 open disputes exceed 1");
+    assert_eq!(account.risk("").open_disputes, 3);
+    assert_eq!(account.locked_by().map(|e| e.tx), Some(tid(1)));
+}
+
+#[test]
+fn chargeback_ratio() {
+    let risk = vec![
+        RiskRule { name: "amount".to_string(), threshold: Threshold::DisputedAmount(dec!(150.0)), status: AccountStatus::UnderReview, currency: None },
+        RiskRule { name: "ratio".to_string(), threshold: Threshold::ChargebackRatio(dec!(0.5)), status: AccountStatus::Closed, currency: None },
+    ];
+    let mut ledger = make_risk_ledger(risk);
+
+    dispute(&mut ledger, DISPUTE, 0).expect("Failed to process dispute");
+    dispute(&mut ledger, DISPUTE, 1).expect("Failed to process dispute");
+    assert_eq!(ledger.accounts.get(&cid(0)).expect("Failed to get account for client").status, AccountStatus::UnderReview);
+
+    dispute(&mut ledger, CHARGEBACK, 0).expect("Failed to process chargeback");
+    assert_eq!(ledger.accounts.get(&cid(0)).expect("Failed to get account for client").status, AccountStatus::Locked);
+
+    dispute(&mut ledger, CHARGEBACK, 1).expect("Failed to process chargeback");
+
+    let rules: Vec<&str> = ledger.risk_events.iter().map(|e| e.rule.as_str()).collect();
+    assert_eq!(rules, vec!["amount", "ratio"]);
+
+    let account: &Account = ledger.accounts.get(&cid(0)).expect("Failed to get account for client");
+    assert_eq!(account.status, AccountStatus::Closed);
+    assert_eq!(account.risk("").charged_back, dec!(200.0));
+    assert_eq!(account.risk("").deposited, dec!(300.0));
+    assert_eq!(account.risk("").open_disputes, 0);
+}
+
+#[test]
+fn chargeback_ratio_review() {
+    let risk = vec![RiskRule { name: "ratio".to_string(), threshold: Threshold::ChargebackRatio(dec!(0.25)), status: AccountStatus::UnderReview, currency: None }];
+    let mut ledger = make_risk_ledger(risk);
+
+    dispute(&mut ledger, DISPUTE, 0).expect("Failed to process dispute");
+    dispute(&mut ledger, CHARGEBACK, 0).expect("Failed to process chargeback");
+
+    // locked is more restrictive than under review already
+    assert!(ledger.risk_events.is_empty());
+    assert_eq!(ledger.accounts.get(&cid(0)).expect("Failed to get account for client").status, AccountStatus::Locked);
+}
+
+#[test]
+fn currencies() {
+    let risk = vec![
+        RiskRule { name: "amount".to_string(), threshold: Threshold::DisputedAmount(dec!(150.0)), status: AccountStatus::UnderReview, currency: Some("USD".to_string()) },
+        RiskRule { name: "ratio".to_string(), threshold: Threshold::ChargebackRatio(dec!(0.4)), status: AccountStatus::Closed, currency: None },
+    ];
+    let mut ledger = Ledger::new(Config { risk, ..Default::default() });
+
+    for (tx, amount, currency) in &[(0, "100.00", "USD"), (1, "100.00", "USD"), (2, "100000", "JPY")] {
+        let deposit = Transaction {
+            tx_type: DEPOSIT.to_string(),
+            client: cid(0),
+            tx: tid(*tx),
+            amount: amount.to_string(),
+            currency: currency.to_string(),
+            ..Default::default()
+        };
+
+        ledger.process(&deposit).expect("Failed to process deposit");
+    }
+
+    // yen aren't compared with a limit in dollars
+    dispute(&mut ledger, DISPUTE, 2).expect("Failed to process dispute");
+    dispute(&mut ledger, DISPUTE, 0).expect("Failed to process dispute");
+    assert!(ledger.risk_events.is_empty());
+
+    // and don't dilute the share of dollars charged back
+    dispute(&mut ledger, CHARGEBACK, 0).expect("Failed to process chargeback");
+
+    assert_eq!(ledger.risk_events, vec![RiskEvent {
+        client: cid(0),
+        tx: tid(0),
+        rule: "ratio".to_string(),
+        currency: "USD".to_string(),
+        status: AccountStatus::Closed,
+        detail: "100.00 of 200.00 deposited charged back, more than a ratio of 0.4".to_string(),
+    }]);
+
+    let account: &Account = ledger.accounts.get(&cid(0)).expect("Failed to get account for client");
+    assert_eq!(account.risk("USD").deposited, dec!(200.00));
+    assert_eq!(account.risk("JPY").disputed, dec!(100000));
+    assert_eq!(account.risk("JPY").charged_back, dec!(0));
+}
+
+#[test]
+fn load_risk_rules() {
+    let json = r#"[
+        { "name": "disputes", "threshold": { "open_disputes": 3 }, "status": "under_review" },
+        { "name": "ratio", "threshold": { "chargeback_ratio": "0.1" }, "status": "locked", "currency": "USD" }
+    ]"#;
+
+    let risk = RiskRule::load(json.as_bytes()).expect("Failed to load risk rules");
+
+    assert_eq!(risk, vec![
+        RiskRule { name: "disputes".to_string(), threshold: Threshold::OpenDisputes(3), status: AccountStatus::UnderReview, currency: None },
+        RiskRule { name: "ratio".to_string(), threshold: Threshold::ChargebackRatio(dec!(0.1)), status: AccountStatus::Locked, currency: Some("USD".to_string()) },
+    ]);
+}
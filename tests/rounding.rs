@@ -0,0 +1,73 @@
+use rust_decimal_macros::dec;
+
+use payments::{Account, AccountRecord, PaymentError, Precision, Rounding};
+
+mod helpers;
+
+use helpers::cid;
+
+fn precision(scale: u32, rounding: Rounding, strict: bool) -> Precision {
+    Precision { scale, rounding, strict }
+}
+
+#[test]
+fn strategies() {
+    let bankers = precision(4, Rounding::Bankers, false);
+    let half_up = precision(4, Rounding::HalfUp, false);
+    let truncate = precision(4, Rounding::Truncate, false);
+
+    assert_eq!(bankers.round(dec!(0.00005)).expect("Failed to round"), dec!(0.0000));
+    assert_eq!(bankers.round(dec!(0.00015)).expect("Failed to round"), dec!(0.0002));
+    assert_eq!(half_up.round(dec!(0.00005)).expect("Failed to round"), dec!(0.0001));
+    assert_eq!(half_up.round(dec!(-0.00005)).expect("Failed to round"), dec!(-0.0001));
+    assert_eq!(truncate.round(dec!(0.00019)).expect("Failed to round"), dec!(0.0001));
+    assert_eq!(truncate.round(dec!(-0.00019)).expect("Failed to round"), dec!(-0.0001));
+}
+
+#[test]
+fn scale() {
+    let precision = precision(2, Rounding::HalfUp, false);
+
+    assert_eq!(precision.round(dec!(1.5)).expect("Failed to round").to_string(), "1.50");
+    assert_eq!(precision.round(dec!(1.005)).expect("Failed to round").to_string(), "1.01");
+}
+
+#[test]
+fn strict() {
+    let precision = precision(4, Rounding::HalfUp, true);
+
+    assert_eq!(precision.round(dec!(1.5)).expect("Failed to round exact amount").to_string(), "1.5000");
+
+    let mut account = Account::new(cid(0));
+    account.balance_mut("").available = dec!(0.00005);
+    account.balance_mut("").total = dec!(0.00005);
+    account.balance_mut("USD").total = dec!(2.5);
+
+    match account.round(&precision) {
+        Ok(_) => panic!("Inexact rounding succeeded in strict mode"),
+        Err(err) => match err {
+            PaymentError::InexactAmount => (),
+            _ => panic!("Inexact rounding failed with wrong error"),
+        }
+    }
+
+    // the account itself is never changed by rounding
+    assert_eq!(account.balance("").available.to_string(), "0.00005");
+    assert_eq!(account.balance("USD").total.to_string(), "2.5");
+}
+
+#[test]
+fn empty_account() {
+    let account = Account::new(cid(0));
+    let records = AccountRecord::rounded(&account, &precision(4, Rounding::HalfUp, true), false).expect("Failed to round empty account");
+
+    let mut writer = csv::Writer::from_writer(vec![]);
+    for record in &records {
+        writer.serialize(record).expect("Failed to serialize account");
+    }
+    let output = String::from_utf8(writer.into_inner().expect("Failed to flush csv")).expect("Bad utf8");
+    assert_eq!(output, format!("client,currency,available,held,pending,total,locked,status,reason,admin\n{},,0.0000,0.0000,0.0000,0.0000,false,active,,false\n", cid(0)));
+
+    // rounding doesn't give the account a balance
+    assert!(account.balances.is_empty());
+}
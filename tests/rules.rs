@@ -0,0 +1,151 @@
+use rust_decimal_macros::dec;
+
+use payments::{Account, Config, Ledger, Outcome, Limit, PaymentError, Rule, RuleHit, Transaction, Window, AUTHORIZE, DEPOSIT, WITHDRAWAL};
+
+mod helpers;
+
+use helpers::{cid, tid};
+
+// bootstrap a ledger with the passed rules and one client that has one deposit tx
+fn make_ruled_ledger(rules: Vec<Rule>) -> Ledger {
+    let config = Config {
+        rules,
+        ..Default::default()
+    };
+    let mut ledger = Ledger::new(config);
+
+    let deposit = Transaction {
+        tx_type: DEPOSIT.to_string(),
+        client: cid(0),
+        tx: tid(0),
+        amount: "1000.00".to_string(),
+        ..Default::default()
+    };
+
+    ledger.process(&deposit).expect("Failed to process deposit");
+
+    ledger
+}
+
+// process an outflow of the passed amount at the passed time
+fn withdraw(ledger: &mut Ledger, tx_type: &str, tx: u32, amount: &str, timestamp: u64) -> Result<Outcome, PaymentError> {
+    let withdrawal = Transaction {
+        tx_type: tx_type.to_string(),
+        client: cid(0),
+        tx: tid(tx),
+        amount: amount.to_string(),
+        timestamp: Some(timestamp),
+        ..Default::default()
+    };
+
+    ledger.process(&withdrawal)
+}
+
+// assert that the passed result is a rejection by the named rule
+fn assert_limit_exceeded(result: Result<Outcome, PaymentError>, name: &str) {
+    match result {
+        Ok(_) => panic!("Tx beyond limit succeeded"),
+        Err(err) => match err {
+            PaymentError::LimitExceeded { rule } => assert_eq!(rule, name),
+            _ => panic!("Tx beyond limit failed with wrong error"),
+        }
+    }
+}
+
+#[test]
+fn max_amount() {
+    let rules = vec![Rule { name: "single".to_string(), limit: Limit::MaxAmount(dec!(100.0)), currency: None }];
+    let mut ledger = make_ruled_ledger(rules);
+
+    withdraw(&mut ledger, WITHDRAWAL, 1, "100.00", 0).expect("Failed to process withdrawal");
+    assert_limit_exceeded(withdraw(&mut ledger, AUTHORIZE, 2, "100.01", 0), "single");
+
+    assert_eq!(ledger.rule_hits, vec![RuleHit { client: cid(0), tx: tid(2), rule: "single".to_string() }]);
+
+    let account: &Account = ledger.accounts.get(&cid(0)).expect("Failed to get account for client");
+    assert_eq!(account.balance("").available, dec!(900.0));
+    assert_eq!(account.balance("").held, dec!(0.0));
+}
+
+#[test]
+fn daily_total() {
+    let rules = vec![Rule { name: "daily".to_string(), limit: Limit::MaxTotal { window: Window::Daily, amount: dec!(300.0) }, currency: None }];
+    let mut ledger = make_ruled_ledger(rules);
+    let day = 86400;
+
+    withdraw(&mut ledger, WITHDRAWAL, 1, "200.00", day + 10).expect("Failed to process withdrawal");
+    assert_limit_exceeded(withdraw(&mut ledger, WITHDRAWAL, 2, "150.00", day + 20), "daily");
+
+    // a rejected withdrawal doesn't count towards the limit
+    withdraw(&mut ledger, WITHDRAWAL, 3, "100.00", day + 30).expect("Failed to process withdrawal");
+
+    // the window resets at midnight
+    withdraw(&mut ledger, WITHDRAWAL, 4, "300.00", 2 * day).expect("Failed to process withdrawal");
+
+    let account: &Account = ledger.accounts.get(&cid(0)).expect("Failed to get account for client");
+    assert_eq!(account.balance("").available, dec!(400.0));
+}
+
+#[test]
+fn rolling_count() {
+    let rules = vec![Rule { name: "burst".to_string(), limit: Limit::MaxCount { window: Window::Rolling(60), count: 2 }, currency: None }];
+    let mut ledger = make_ruled_ledger(rules);
+
+    withdraw(&mut ledger, WITHDRAWAL, 1, "1.00", 100).expect("Failed to process withdrawal");
+    withdraw(&mut ledger, WITHDRAWAL, 2, "1.00", 130).expect("Failed to process withdrawal");
+    assert_limit_exceeded(withdraw(&mut ledger, WITHDRAWAL, 3, "1.00", 159), "burst");
+    withdraw(&mut ledger, WITHDRAWAL, 4, "1.00", 160).expect("Failed to process withdrawal");
+}
+
+#[test]
+fn load_rules() {
+    let json = r#"[
+        { "name": "single", "limit": { "max_amount": "500.00" } },
+        { "name": "daily", "limit": { "max_total": { "window": "daily", "amount": "2000" } } },
+        { "name": "burst", "limit": { "max_count": { "window": { "rolling": 300 }, "count": 3 } }, "currency": "EUR" }
+    ]"#;
+
+    let rules = Rule::load(json.as_bytes()).expect("Failed to load rules");
+
+    assert_eq!(rules, vec![
+        Rule { name: "single".to_string(), limit: Limit::MaxAmount(dec!(500.0)), currency: None },
+        Rule { name: "daily".to_string(), limit: Limit::MaxTotal { window: Window::Daily, amount: dec!(2000) }, currency: None },
+        Rule { name: "burst".to_string(), limit: Limit::MaxCount { window: Window::Rolling(300), count: 3 }, currency: Some("EUR".to_string()) },
+    ]);
+}
+
+#[test]
+fn currencies() {
+    let rules = vec![
+        Rule { name: "daily".to_string(), limit: Limit::MaxTotal { window: Window::Daily, amount: dec!(300.0) }, currency: Some("USD".to_string()) },
+        Rule { name: "burst".to_string(), limit: Limit::MaxCount { window: Window::Rolling(60), count: 2 }, currency: None },
+    ];
+    let mut ledger = Ledger::new(Config { rules, ..Default::default() });
+
+    let process = |ledger: &mut Ledger, tx_type: &str, tx: u32, amount: &str, currency: &str| {
+        ledger.process(&Transaction {
+            tx_type: tx_type.to_string(),
+            client: cid(0),
+            tx: tid(tx),
+            amount: amount.to_string(),
+            currency: currency.to_string(),
+            timestamp: Some(0),
+            ..Default::default()
+        })
+    };
+
+    process(&mut ledger, DEPOSIT, 0, "1000", "USD").expect("Failed to process deposit");
+    process(&mut ledger, DEPOSIT, 1, "100000", "JPY").expect("Failed to process deposit");
+
+    // amounts in different currencies are never added up or counted together
+    process(&mut ledger, WITHDRAWAL, 2, "200", "USD").expect("Failed to process withdrawal");
+    process(&mut ledger, WITHDRAWAL, 3, "5000", "JPY").expect("Failed to process withdrawal");
+    assert_limit_exceeded(process(&mut ledger, WITHDRAWAL, 4, "150", "USD"), "daily");
+    process(&mut ledger, WITHDRAWAL, 5, "5000", "JPY").expect("Failed to process withdrawal");
+    assert_limit_exceeded(process(&mut ledger, WITHDRAWAL, 6, "5000", "JPY"), "burst");
+    process(&mut ledger, WITHDRAWAL, 7, "50", "USD").expect("Failed to process withdrawal");
+
+    let account: &Account = ledger.accounts.get(&cid(0)).expect("Failed to get account for client");
+    assert_eq!(account.balance("USD").available, dec!(750));
+    assert_eq!(account.balance("JPY").available, dec!(90000));
+}
@@ -0,0 +1,71 @@
+use payments::{Schema, Schemas, Transaction, DEPOSIT, WITHDRAWAL};
+
+mod helpers;
+
+use helpers::{cid, tid};
+
+fn make_schemas() -> Schemas {
+    let data = r#"{
+        "partner.csv": {
+            "rename": {"txn_type": "type", "customer_id": "client", "reference": "tx", "value": "amount"},
+            "fixed": {"currency": "EUR"},
+            "ignore": ["note"]
+        },
+        "legacy.csv": {"columns": ["type", "client", "tx", "amount"]}
+    }"#;
+
+    Schemas::load(data.as_bytes()).expect("Failed to load schemas")
+}
+
+fn read(schema: &Schema, data: &str) -> Vec<Transaction> {
+    schema.read_transactions(data.as_bytes())
+        .expect("Failed to check header")
+        .collect::<Result<_, _>>()
+        .expect("Failed to read txs")
+}
+
+#[test]
+fn renamed_columns() {
+    let schemas = make_schemas();
+    let schema = schemas.get("inbox/partner.csv").expect("Failed to find schema by file name");
+    let txs = read(schema, &format!("txn_type,note,customer_id,reference,value\ndeposit,first,{0},{1},10.5\nwithdrawal,,{0},{2},2.0\n", cid(1), tid(1), tid(2)));
+
+    assert_eq!(txs.len(), 2);
+    assert_eq!(txs[0].tx_type, DEPOSIT);
+    assert_eq!(txs[0].client, cid(1));
+    assert_eq!(txs[0].tx, tid(1));
+    assert_eq!(txs[0].amount, "10.5");
+    assert_eq!(txs[0].currency, "EUR");
+    assert_eq!(txs[1].tx_type, WITHDRAWAL);
+}
+
+#[test]
+fn positional_columns() {
+    let schemas = make_schemas();
+    let schema = schemas.get("legacy.csv").expect("Failed to find schema");
+    let txs = read(schema, &format!("deposit,{},{},1.25\n", cid(3), tid(4)));
+
+    assert_eq!(txs[0].client, cid(3));
+    assert_eq!(txs[0].tx, tid(4));
+    assert_eq!(txs[0].amount, "1.25");
+
+    assert!(schema.read_transactions(format!("deposit,{},{}\n", cid(3), tid(4)).as_bytes()).expect("Failed to check header").next().expect("Failed to read row").is_err());
+    assert!(schemas.get("other.csv").is_none());
+}
+
+#[test]
+fn header_mismatch() {
+    let schemas = make_schemas();
+    let schema = schemas.get("partner.csv").expect("Failed to find schema");
+    let check = |header: &str| schema.read_transactions(header.as_bytes()).is_ok();
+
+    assert!(check("txn_type,customer_id,reference,value,note\n"));
+    // unknown, missing, duplicate and fixed columns are all rejected before any row is read
+    assert!(!check("txn_type,customer_id,reference,value,note,extra\n"));
+    assert!(!check("txn_type,customer_id,reference,note\n"));
+    assert!(!check("txn_type,customer_id,reference,value,note,tx\n"));
+    assert!(!check("txn_type,customer_id,reference,value,note,currency\n"));
+
+    let schema = Schema { fixed: vec![("tx".to_string(), "1".to_string())].into_iter().collect(), ..Default::default() };
+    assert!(schema.read_transactions("type,amount\n".as_bytes()).is_err());
+}
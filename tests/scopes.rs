@@ -0,0 +1,93 @@
+use rust_decimal_macros::dec;
+
+use payments::{Account, Config, IdScope, Ledger, PaymentError, Transaction, DEPOSIT, DISPUTE};
+
+mod helpers;
+
+use helpers::{cid, tid};
+
+fn make_scoped_ledger(id_scope: IdScope) -> Ledger {
+    Ledger::new(Config { id_scope, ..Default::default() })
+}
+
+fn deposit(client: u16, tx: u32, amount: &str, source: &str) -> Transaction {
+    Transaction {
+        tx_type: DEPOSIT.to_string(),
+        client: cid(client),
+        tx: tid(tx),
+        amount: amount.to_string(),
+        source: Some(source.to_string()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn global_scope() {
+    let mut ledger = make_scoped_ledger(IdScope::Global);
+
+    ledger.process(&deposit(0, 1, "10.0", "a.csv")).expect("Failed to process deposit");
+
+    match ledger.process(&deposit(1, 1, "20.0", "b.csv")) {
+        Ok(_) => panic!("Reused global id succeeded"),
+        Err(err) => match err {
+            PaymentError::DuplicateTransaction { .. } => (),
+            _ => panic!("Reused global id failed with wrong error"),
+        }
+    }
+}
+
+#[test]
+fn client_scope() {
+    let mut ledger = make_scoped_ledger(IdScope::Client);
+
+    ledger.process(&deposit(0, 1, "10.0", "a.csv")).expect("Failed to process deposit for client 0");
+    ledger.process(&deposit(1, 1, "20.0", "a.csv")).expect("Failed to process deposit for client 1");
+
+    let dispute = Transaction {
+        tx_type: DISPUTE.to_string(),
+        client: cid(1),
+        tx: tid(1),
+        ..Default::default()
+    };
+
+    ledger.process(&dispute).expect("Failed to process dispute");
+
+    let account: &Account = ledger.accounts.get(&cid(0)).expect("Failed to get account for client 0");
+    assert_eq!(account.balance("").available, dec!(10.0));
+    assert_eq!(account.balance("").held, dec!(0.0));
+
+    let account: &Account = ledger.accounts.get(&cid(1)).expect("Failed to get account for client 1");
+    assert_eq!(account.balance("").available, dec!(0.0));
+    assert_eq!(account.balance("").held, dec!(20.0));
+
+    match ledger.process(&deposit(1, 1, "30.0", "b.csv")) {
+        Ok(_) => panic!("Reused client id succeeded"),
+        Err(err) => match err {
+            PaymentError::DuplicateTransaction { .. } => (),
+            _ => panic!("Reused client id failed with wrong error"),
+        }
+    }
+}
+
+#[test]
+fn source_scope() {
+    let mut ledger = make_scoped_ledger(IdScope::Source);
+
+    ledger.process(&deposit(0, 1, "10.0", "a.csv")).expect("Failed to process deposit from a.csv");
+    ledger.process(&deposit(0, 1, "20.0", "b.csv")).expect("Failed to process deposit from b.csv");
+
+    let dispute = Transaction {
+        tx_type: DISPUTE.to_string(),
+        client: cid(0),
+        tx: tid(1),
+        source: Some("b.csv".to_string()),
+        ..Default::default()
+    };
+
+    ledger.process(&dispute).expect("Failed to process dispute");
+
+    let account: &Account = ledger.accounts.get(&cid(0)).expect("Failed to get account for client");
+    assert_eq!(account.balance("").available, dec!(10.0));
+    assert_eq!(account.balance("").held, dec!(20.0));
+    assert_eq!(account.balance("").total, dec!(30.0));
+}
@@ -0,0 +1,217 @@
+use rust_decimal_macros::dec;
+
+use payments::{Account, Config, Ledger, PaymentError, Settlement, Transaction, DEPOSIT, DISPUTE, RETURNED, SETTLE, WITHDRAWAL};
+
+mod helpers;
+
+use helpers::{cid, tid};
+
+// bootstrap a ledger with one client that has one pending deposit
+fn make_pending_ledger(settlement: Settlement) -> Ledger {
+    let config = Config {
+        settlement,
+        ..Default::default()
+    };
+    let mut ledger = Ledger::new(config);
+
+    let deposit = Transaction {
+        tx_type: DEPOSIT.to_string(),
+        client: cid(0),
+        tx: tid(0),
+        amount: "100.00".to_string(),
+        timestamp: Some(1000),
+        ..Default::default()
+    };
+
+    ledger.process(&deposit).expect("Failed to process deposit");
+
+    let account: &Account = ledger.accounts.get(&cid(0)).expect("Failed to get account for client");
+    assert_eq!(account.balance("").available, dec!(0.0));
+    assert_eq!(account.balance("").pending, dec!(100.0));
+    assert_eq!(account.balance("").total, dec!(100.0));
+
+    ledger
+}
+
+// a small deposit into another account, to move the ledger along
+fn tick(ledger: &mut Ledger, tx: u32, timestamp: Option<u64>) {
+    let deposit = Transaction {
+        tx_type: DEPOSIT.to_string(),
+        client: cid(1),
+        tx: tid(tx),
+        amount: "1.00".to_string(),
+        timestamp,
+        ..Default::default()
+    };
+
+    ledger.process(&deposit).expect("Failed to process deposit");
+}
+
+#[test]
+fn pending_not_withdrawable() {
+    let mut ledger = make_pending_ledger(Settlement::Explicit);
+
+    let withdrawal = Transaction {
+        tx_type: WITHDRAWAL.to_string(),
+        client: cid(0),
+        tx: tid(1),
+        amount: "50.00".to_string(),
+        ..Default::default()
+    };
+
+    match ledger.process(&withdrawal) {
+        Ok(_) => panic!("Withdrawal of pending funds succeeded"),
+        Err(err) => match err {
+            PaymentError::InsufficientFunds => (),
+            _ => panic!("Withdrawal of pending funds failed with wrong error"),
+        }
+    }
+
+    let dispute = Transaction {
+        tx_type: DISPUTE.to_string(),
+        client: cid(0),
+        tx: tid(0),
+        ..Default::default()
+    };
+
+    match ledger.process(&dispute) {
+        Ok(_) => panic!("Dispute of pending deposit succeeded"),
+        Err(err) => match err {
+            PaymentError::DepositNotSettled => (),
+            _ => panic!("Dispute of pending deposit failed with wrong error"),
+        }
+    }
+}
+
+#[test]
+fn settle_explicit() {
+    let mut ledger = make_pending_ledger(Settlement::Explicit);
+
+    let settle = Transaction {
+        tx_type: SETTLE.to_string(),
+        client: cid(0),
+        tx: tid(0),
+        ..Default::default()
+    };
+
+    ledger.process(&settle).expect("Failed to settle deposit");
+
+    {
+        let account: &Account = ledger.accounts.get(&cid(0)).expect("Failed to get account for client");
+        assert_eq!(account.balance("").available, dec!(100.0));
+        assert_eq!(account.balance("").pending, dec!(0.0));
+        assert_eq!(account.balance("").total, dec!(100.0));
+    }
+
+    match ledger.process(&settle) {
+        Ok(_) => panic!("Second settlement succeeded"),
+        Err(err) => match err {
+            PaymentError::DepositNotPending => (),
+            _ => panic!("Second settlement failed with wrong error"),
+        }
+    }
+}
+
+#[test]
+fn settle_after_transactions() {
+    let mut ledger = make_pending_ledger(Settlement::Transactions(2));
+
+    tick(&mut ledger, 1, None);
+    assert_eq!(ledger.accounts.get(&cid(0)).expect("Failed to get account for client").balance("").pending, dec!(100.0));
+
+    tick(&mut ledger, 2, None);
+
+    let account: &Account = ledger.accounts.get(&cid(0)).expect("Failed to get account for client");
+    assert_eq!(account.balance("").available, dec!(100.0));
+    assert_eq!(account.balance("").pending, dec!(0.0));
+}
+
+#[test]
+fn settle_after_seconds() {
+    let mut ledger = make_pending_ledger(Settlement::Seconds(60));
+
+    tick(&mut ledger, 1, Some(1059));
+    tick(&mut ledger, 2, None);
+    assert_eq!(ledger.accounts.get(&cid(0)).expect("Failed to get account for client").balance("").pending, dec!(100.0));
+
+    tick(&mut ledger, 3, Some(1060));
+
+    let account: &Account = ledger.accounts.get(&cid(0)).expect("Failed to get account for client");
+    assert_eq!(account.balance("").available, dec!(100.0));
+    assert_eq!(account.balance("").pending, dec!(0.0));
+}
+
+#[test]
+fn returned() {
+    let mut ledger = make_pending_ledger(Settlement::Seconds(60));
+
+    let returned = Transaction {
+        tx_type: RETURNED.to_string(),
+        client: cid(1),
+        tx: tid(0),
+        ..Default::default()
+    };
+
+    match ledger.process(&returned) {
+        Ok(_) => panic!("Return by wrong client succeeded"),
+        Err(err) => match err {
+            PaymentError::PendingWrongClient => (),
+            _ => panic!("Return by wrong client failed with wrong error"),
+        }
+    }
+
+    let returned = Transaction {
+        client: cid(0),
+        ..returned
+    };
+
+    ledger.process(&returned).expect("Failed to return deposit");
+    tick(&mut ledger, 1, Some(2000));
+
+    let account: &Account = ledger.accounts.get(&cid(0)).expect("Failed to get account for client");
+    assert_eq!(account.balance("").available, dec!(0.0));
+    assert_eq!(account.balance("").pending, dec!(0.0));
+    assert_eq!(account.balance("").total, dec!(0.0));
+}
+
+#[test]
+fn returned_then_disputed() {
+    let mut ledger = make_pending_ledger(Settlement::Explicit);
+
+    let returned = Transaction {
+        tx_type: RETURNED.to_string(),
+        client: cid(0),
+        tx: tid(0),
+        ..Default::default()
+    };
+
+    ledger.process(&returned).expect("Failed to return deposit");
+
+    let dispute = Transaction {
+        tx_type: DISPUTE.to_string(),
+        ..returned
+    };
+
+    match ledger.process(&dispute) {
+        Ok(_) => panic!("Dispute of returned deposit succeeded"),
+        Err(err) => match err {
+            PaymentError::NotDisputable => (),
+            _ => panic!("Dispute of returned deposit failed with wrong error"),
+        }
+    }
+
+    let account: &Account = ledger.accounts.get(&cid(0)).expect("Failed to get account for client");
+    assert_eq!(account.balance("").available, dec!(0.0));
+    assert_eq!(account.balance("").held, dec!(0.0));
+    assert_eq!(account.balance("").total, dec!(0.0));
+    assert_eq!(account.risk("").deposited, dec!(0.0));
+}
+
+#[test]
+fn parse_settlement() {
+    assert_eq!("immediate".parse(), Ok(Settlement::Immediate));
+    assert_eq!("explicit".parse(), Ok(Settlement::Explicit));
+    assert_eq!("5tx".parse(), Ok(Settlement::Transactions(5)));
+    assert_eq!("3600s".parse(), Ok(Settlement::Seconds(3600)));
+    assert!("soon".parse::<Settlement>().is_err());
+}
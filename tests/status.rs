@@ -0,0 +1,258 @@
+use rust_decimal_macros::dec;
+
+use payments::{Account, AccountStatus, PaymentError, Transaction, CHARGEBACK, CLOSE, DEPOSIT, DISPUTE, FREEZE, RESOLVE, UNLOCK, WITHDRAWAL};
+
+mod helpers;
+
+use helpers::{cid, tid, make_ledger, make_disputed_ledger};
+
+#[test]
+fn transitions() {
+    assert!(AccountStatus::Active.can_transition(AccountStatus::UnderReview));
+    assert!(AccountStatus::Frozen.can_transition(AccountStatus::Active));
+    assert!(AccountStatus::Locked.can_transition(AccountStatus::Active));
+    assert!(AccountStatus::Locked.can_transition(AccountStatus::Closed));
+    assert!(!AccountStatus::Locked.can_transition(AccountStatus::Frozen));
+    assert!(!AccountStatus::Closed.can_transition(AccountStatus::Active));
+    assert!(!AccountStatus::Closed.can_transition(AccountStatus::Closed));
+
+    let mut account = Account::new(cid(0));
+    account.set_status(AccountStatus::Locked, "manual".to_string()).expect("Failed to lock account");
+    assert_eq!(account.reason, "manual");
+
+    match account.set_status(AccountStatus::UnderReview, "review".to_string()) {
+        Ok(_) => panic!("Locked to under review succeeded"),
+        Err(err) => match err {
+            PaymentError::InvalidStatusTransition => (),
+            _ => panic!("Locked to under review failed with wrong error"),
+        }
+    }
+
+    account.set_status(AccountStatus::Active, "ignored".to_string()).expect("Failed to unlock account");
+    assert_eq!(account.reason, "");
+}
+
+#[test]
+fn under_review() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_ledger(client, tx, dec!(100.0));
+
+    ledger.accounts.get_mut(&client).expect("Failed to get account for client").status = AccountStatus::UnderReview;
+
+    let deposit = Transaction {
+        tx_type: DEPOSIT.to_string(),
+        client,
+        tx: tid(1),
+        amount: "10.00".to_string(),
+        ..Default::default()
+    };
+
+    ledger.process(&deposit).expect("Failed to deposit into account under review");
+
+    let withdrawal = Transaction {
+        tx_type: WITHDRAWAL.to_string(),
+        client,
+        tx: tid(2),
+        amount: "10.00".to_string(),
+        ..Default::default()
+    };
+
+    match ledger.process(&withdrawal) {
+        Ok(_) => panic!("Withdrawal from account under review succeeded"),
+        Err(err) => match err {
+            PaymentError::AccountUnderReview => (),
+            _ => panic!("Withdrawal from account under review failed with wrong error"),
+        }
+    }
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert_eq!(account.balance("").available, dec!(110.0));
+}
+
+#[test]
+fn resolve_keeps_chargeback_lock() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_ledger(client, tx, dec!(100.0));
+
+    let withdrawal = Transaction {
+        tx_type: WITHDRAWAL.to_string(),
+        client,
+        tx: tid(1),
+        amount: "50.00".to_string(),
+        ..Default::default()
+    };
+
+    ledger.process(&withdrawal).expect("Failed to process withdrawal");
+
+    for tx_type in &[DISPUTE, CHARGEBACK] {
+        let dispute = Transaction {
+            tx_type: tx_type.to_string(),
+            client,
+            tx,
+            ..Default::default()
+        };
+
+        ledger.process(&dispute).expect("Failed to charge back deposit");
+    }
+
+    for tx_type in &[DISPUTE, RESOLVE] {
+        let dispute = Transaction {
+            tx_type: tx_type.to_string(),
+            client,
+            tx: tid(1),
+            ..Default::default()
+        };
+
+        ledger.process(&dispute).expect("Failed to resolve withdrawal");
+    }
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert_eq!(account.status, AccountStatus::Locked);
+    assert_eq!(account.reason, format!("chargeback of tx {}", tx));
+}
+
+#[test]
+fn resolve_keeps_admin_freeze() {
+    let client = cid(0);
+    let mut ledger = make_ledger(client, tid(0), dec!(100.0));
+
+    let withdrawal = Transaction {
+        tx_type: WITHDRAWAL.to_string(),
+        client,
+        tx: tid(1),
+        amount: "50.00".to_string(),
+        ..Default::default()
+    };
+
+    ledger.process(&withdrawal).expect("Failed to process withdrawal");
+
+    let freeze = Transaction {
+        tx_type: FREEZE.to_string(),
+        client,
+        tx: tid(2),
+        reason: "fraud".to_string(),
+        admin: true,
+        ..Default::default()
+    };
+
+    ledger.process(&freeze).expect("Failed to freeze account");
+
+    for tx_type in &[DISPUTE, RESOLVE] {
+        let dispute = Transaction {
+            tx_type: tx_type.to_string(),
+            client,
+            tx: tid(1),
+            ..Default::default()
+        };
+
+        ledger.process(&dispute).expect("Failed to resolve withdrawal");
+    }
+
+    let withdrawal = Transaction {
+        tx_type: WITHDRAWAL.to_string(),
+        client,
+        tx: tid(3),
+        amount: "10.00".to_string(),
+        ..Default::default()
+    };
+
+    match ledger.process(&withdrawal) {
+        Ok(_) => panic!("Withdrawal from frozen account succeeded"),
+        Err(err) => match err {
+            PaymentError::AccountFrozen => (),
+            _ => panic!("Withdrawal from frozen account failed with wrong error"),
+        }
+    }
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert_eq!(account.status, AccountStatus::Frozen);
+    assert_eq!(account.reason, "fraud");
+}
+
+#[test]
+fn resolve_keeps_other_disputes() {
+    let client = cid(0);
+    let mut ledger = make_ledger(client, tid(0), dec!(100.0));
+
+    for tx in &[tid(1), tid(2)] {
+        let withdrawal = Transaction {
+            tx_type: WITHDRAWAL.to_string(),
+            client,
+            tx: *tx,
+            amount: "10.00".to_string(),
+            ..Default::default()
+        };
+
+        ledger.process(&withdrawal).expect("Failed to process withdrawal");
+    }
+
+    for (tx_type, tx) in &[(DISPUTE, tid(1)), (DISPUTE, tid(2)), (RESOLVE, tid(1))] {
+        let dispute = Transaction {
+            tx_type: tx_type.to_string(),
+            client,
+            tx: *tx,
+            ..Default::default()
+        };
+
+        ledger.process(&dispute).expect("Failed to process dispute");
+    }
+
+    // the other withdrawal is still disputed
+    assert_eq!(ledger.accounts.get(&client).expect("Failed to get account for client").status, AccountStatus::Frozen);
+
+    let resolve = Transaction {
+        tx_type: RESOLVE.to_string(),
+        client,
+        tx: tid(2),
+        ..Default::default()
+    };
+
+    ledger.process(&resolve).expect("Failed to resolve withdrawal");
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert_eq!(account.status, AccountStatus::Active);
+    assert_eq!(account.withdrawal_disputes, 0);
+}
+
+#[test]
+fn closed() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_disputed_ledger(client, tx, dec!(100.0));
+
+    let close = Transaction {
+        tx_type: CLOSE.to_string(),
+        client,
+        tx: tid(1),
+        reason: "CUSTOMER_REQUEST".to_string(),
+        admin: true,
+        ..Default::default()
+    };
+
+    ledger.process(&close).expect("Failed to close account");
+
+    for tx_type in &[RESOLVE, UNLOCK, FREEZE] {
+        let rejected = Transaction {
+            tx_type: tx_type.to_string(),
+            client,
+            tx: tid(2),
+            admin: true,
+            ..Default::default()
+        };
+
+        match ledger.process(&rejected) {
+            Ok(_) => panic!("Tx on closed account succeeded"),
+            Err(err) => match err {
+                PaymentError::AccountClosed => (),
+                _ => panic!("Tx on closed account failed with wrong error"),
+            }
+        }
+    }
+
+    let account: &Account = ledger.accounts.get(&client).expect("Failed to get account for client");
+    assert_eq!(account.status, AccountStatus::Closed);
+    assert_eq!(account.reason, "CUSTOMER_REQUEST");
+    assert_eq!(account.balance("").held, dec!(100.0));
+}
@@ -0,0 +1,323 @@
+use rust_decimal_macros::dec;
+
+use payments::{check_duplicate, check_funds, check_schema, check_status, Account, AccountStatus, Config, Ledger, Outcome, PaymentError, Transaction, ADJUST, AUTHORIZE, CAPTURE, DEPOSIT, DISPUTE, EXCHANGE, WITHDRAWAL};
+
+mod helpers;
+
+use helpers::{cid, tid, make_ledger};
+
+#[test]
+fn schema() {
+    let deposit = Transaction {
+        tx_type: DEPOSIT.to_string(),
+        amount: "1.5".to_string(),
+        ..Default::default()
+    };
+
+    assert_eq!(check_schema(&deposit).expect("Valid deposit failed schema check"), Some(dec!(1.5)));
+
+    let capture = Transaction {
+        tx_type: CAPTURE.to_string(),
+        ..Default::default()
+    };
+
+    assert_eq!(check_schema(&capture).expect("Full capture failed schema check"), None);
+
+    let bad = Transaction {
+        tx_type: WITHDRAWAL.to_string(),
+        amount: "X".to_string(),
+        ..Default::default()
+    };
+
+    match check_schema(&bad) {
+        Ok(_) => panic!("Bad amount passed schema check"),
+        Err(err) => match err {
+            PaymentError::BadDecimal => (),
+            _ => panic!("Bad amount failed with wrong error"),
+        }
+    }
+
+    let adjust = Transaction {
+        tx_type: ADJUST.to_string(),
+        amount: "1.0".to_string(),
+        admin: true,
+        ..Default::default()
+    };
+
+    match check_schema(&adjust) {
+        Ok(_) => panic!("Adjustment without reason passed schema check"),
+        Err(err) => match err {
+            PaymentError::MissingReason => (),
+            _ => panic!("Adjustment without reason failed with wrong error"),
+        }
+    }
+
+    let unknown = Transaction {
+        tx_type: "withdraw".to_string(),
+        ..Default::default()
+    };
+
+    match check_schema(&unknown) {
+        Ok(_) => panic!("Unknown tx type passed schema check"),
+        Err(err) => match err {
+            PaymentError::UnknownTxType => (),
+            _ => panic!("Unknown tx type failed with wrong error"),
+        }
+    }
+}
+
+// check the passed tx type is refused a zero or negative amount
+fn check_non_positive(tx_type: &str) {
+    for amount in &["0", "-1.5"] {
+        let tx = Transaction {
+            tx_type: tx_type.to_string(),
+            amount: amount.to_string(),
+            currency: "USD".to_string(),
+            to_currency: "EUR".to_string(),
+            ..Default::default()
+        };
+
+        match check_schema(&tx) {
+            Ok(_) => panic!("Amount {} passed schema check for {}", amount, tx_type),
+            Err(err) => match err {
+                PaymentError::NonPositiveAmount => (),
+                _ => panic!("Amount {} failed with wrong error for {}", amount, tx_type),
+            }
+        }
+    }
+}
+
+#[test]
+fn non_positive_deposit() {
+    check_non_positive(DEPOSIT);
+}
+
+#[test]
+fn non_positive_withdrawal() {
+    check_non_positive(WITHDRAWAL);
+}
+
+#[test]
+fn non_positive_authorize() {
+    check_non_positive(AUTHORIZE);
+}
+
+#[test]
+fn non_positive_exchange() {
+    check_non_positive(EXCHANGE);
+}
+
+#[test]
+fn non_positive_capture() {
+    check_non_positive(CAPTURE);
+}
+
+#[test]
+fn negative_adjust() {
+    let adjust = Transaction {
+        tx_type: ADJUST.to_string(),
+        amount: "-1.5".to_string(),
+        reason: "correction".to_string(),
+        admin: true,
+        ..Default::default()
+    };
+
+    assert_eq!(check_schema(&adjust).expect("Debit adjustment failed schema check"), Some(dec!(-1.5)));
+}
+
+#[test]
+fn duplicate() {
+    let stored = Transaction {
+        tx_type: DEPOSIT.to_string(),
+        client: cid(0),
+        tx: tid(0),
+        amount: "1.0".to_string(),
+        ..Default::default()
+    };
+
+    let replay = Transaction {
+        amount: "1.00".to_string(),
+        ..stored.clone()
+    };
+
+    assert_eq!(check_duplicate(&replay, Some(&stored), false).expect("Replay failed duplicate check"), Outcome::AlreadyApplied);
+    assert_eq!(check_duplicate(&replay, None, false).expect("New tx failed duplicate check"), Outcome::Applied);
+
+    let other = Transaction {
+        client: cid(1),
+        ..stored.clone()
+    };
+
+    match check_duplicate(&other, Some(&stored), false) {
+        Ok(_) => panic!("Duplicate id passed duplicate check"),
+        Err(err) => match err {
+            PaymentError::DuplicateTransaction { .. } => (),
+            _ => panic!("Duplicate id failed with wrong error"),
+        }
+    }
+
+    // an exchange into another currency isn't a replay of the stored one
+    let exchange = Transaction {
+        tx_type: EXCHANGE.to_string(),
+        currency: "USD".to_string(),
+        to_currency: "EUR".to_string(),
+        ..stored.clone()
+    };
+
+    let other_currency = Transaction {
+        to_currency: "GBP".to_string(),
+        ..exchange.clone()
+    };
+
+    assert_eq!(check_duplicate(&exchange, Some(&exchange), false).expect("Exchange replay failed duplicate check"), Outcome::AlreadyApplied);
+
+    match check_duplicate(&other_currency, Some(&exchange), false) {
+        Ok(_) => panic!("Exchange into another currency passed duplicate check"),
+        Err(err) => match err {
+            PaymentError::DuplicateTransaction { .. } => (),
+            _ => panic!("Exchange into another currency failed with wrong error"),
+        }
+    }
+
+    match check_duplicate(&other, None, true) {
+        Ok(_) => panic!("Burned id passed duplicate check"),
+        Err(err) => match err {
+            PaymentError::TransactionIdBurned => (),
+            _ => panic!("Burned id failed with wrong error"),
+        }
+    }
+
+    // disputes refer to another tx's id rather than owning one
+    let dispute = Transaction {
+        tx_type: DISPUTE.to_string(),
+        ..stored.clone()
+    };
+
+    assert_eq!(check_duplicate(&dispute, Some(&stored), true).expect("Dispute failed duplicate check"), Outcome::Applied);
+}
+
+#[test]
+fn status_and_funds() {
+    let mut account = Account::new(cid(0));
+    account.balance_mut("").available = dec!(10.0);
+
+    let withdrawal = Transaction {
+        tx_type: WITHDRAWAL.to_string(),
+        amount: "20.0".to_string(),
+        ..Default::default()
+    };
+
+    check_status(&account, &withdrawal).expect("Active account failed status check");
+
+    match check_funds(&account, &withdrawal, Some(dec!(20.0))) {
+        Ok(_) => panic!("Overdraft passed funds check"),
+        Err(err) => match err {
+            PaymentError::InsufficientFunds => (),
+            _ => panic!("Overdraft failed with wrong error"),
+        }
+    }
+
+    check_funds(&account, &withdrawal, Some(dec!(10.0))).expect("Withdrawal of available funds failed funds check");
+
+    account.status = AccountStatus::UnderReview;
+
+    match check_status(&account, &withdrawal) {
+        Ok(_) => panic!("Withdrawal passed status check of account under review"),
+        Err(err) => match err {
+            PaymentError::AccountUnderReview => (),
+            _ => panic!("Withdrawal from account under review failed with wrong error"),
+        }
+    }
+}
+
+#[test]
+fn duplicate_before_funds() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_ledger(client, tx, dec!(100.0));
+
+    let withdrawal = Transaction {
+        tx_type: WITHDRAWAL.to_string(),
+        client,
+        tx,
+        amount: "200.00".to_string(),
+        ..Default::default()
+    };
+
+    match ledger.process(&withdrawal) {
+        Ok(_) => panic!("Duplicate overdraft succeeded"),
+        Err(err) => match err {
+            PaymentError::DuplicateTransaction { .. } => (),
+            _ => panic!("Duplicate overdraft failed with wrong error"),
+        }
+    }
+}
+
+#[test]
+fn rejected_id_reusable() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = make_ledger(client, tx, dec!(100.0));
+
+    let withdrawal = Transaction {
+        tx_type: WITHDRAWAL.to_string(),
+        client,
+        tx: tid(1),
+        amount: "200.00".to_string(),
+        ..Default::default()
+    };
+
+    assert!(ledger.process(&withdrawal).is_err());
+
+    let deposit = Transaction {
+        tx_type: DEPOSIT.to_string(),
+        client: cid(1),
+        tx: tid(1),
+        amount: "5.00".to_string(),
+        ..Default::default()
+    };
+
+    ledger.process(&deposit).expect("Failed to reuse id of rejected tx");
+}
+
+#[test]
+fn burn_rejected_ids() {
+    let client = cid(0);
+    let tx = tid(0);
+    let mut ledger = Ledger::new(Config { burn_rejected_ids: true, ..Default::default() });
+
+    let withdrawal = Transaction {
+        tx_type: WITHDRAWAL.to_string(),
+        client,
+        tx,
+        amount: "200.00".to_string(),
+        ..Default::default()
+    };
+
+    match ledger.process(&withdrawal) {
+        Ok(_) => panic!("Overdraft succeeded"),
+        Err(err) => match err {
+            PaymentError::InsufficientFunds => (),
+            _ => panic!("Overdraft failed with wrong error"),
+        }
+    }
+
+    let deposit = Transaction {
+        tx_type: DEPOSIT.to_string(),
+        client: cid(1),
+        tx,
+        amount: "5.00".to_string(),
+        ..Default::default()
+    };
+
+    match ledger.process(&deposit) {
+        Ok(_) => panic!("Reuse of burned id succeeded"),
+        Err(err) => match err {
+            PaymentError::TransactionIdBurned => (),
+            _ => panic!("Reuse of burned id failed with wrong error"),
+        }
+    }
+
+    let account: &Account = ledger.accounts.get(&cid(1)).expect("Failed to get account for client");
+    assert_eq!(account.balance("").total, dec!(0.0));
+}